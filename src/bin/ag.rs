@@ -1,5 +1,5 @@
 use async_openai::config::OpenAIConfig;
-use async_openai::types::ChatCompletionRequestUserMessageArgs;
+use async_openai::types::{ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs};
 use async_openai::{types::CreateChatCompletionRequestArgs, Client};
 use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
@@ -7,9 +7,10 @@ use futures::stream::StreamExt;
 use prettytable::{row, Table};
 use serde::{Deserialize, Serialize};
 use shellexpand::tilde;
-use std::fs::{create_dir_all, read_to_string, write};
-use std::io::{stdout, Write};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_dir, read_to_string, remove_file, write};
+use std::io::{stdin, stdout, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command as Process;
 
 #[derive(Parser)]
@@ -44,23 +45,51 @@ struct Cli {
 enum Commands {
     #[command(visible_alias = "c", about = "Chat with the OpenAI API")]
     Chat {
-        /// The prompt to send to the OpenAI API
+        /// The prompt to send to the OpenAI API, read from stdin when omitted
         prompt: Vec<String>,
 
-        /// Maximum number of tokens to generate in the completion.
-        #[arg(short, long, default_value_t = 1000)]
-        max_tokens: u32,
+        /// Maximum number of tokens to generate in the completion. [default: 1000]
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
 
-        /// What sampling temperature to use, between 0 and 2. Higher values like 0.8 will make the output more random, while lower values like 0.2 will make it more focused and deterministic. (Optional)
-        #[arg(short, long, default_value_t = 0.7)]
-        temperature: f32,
+        /// What sampling temperature to use, between 0 and 2. Higher values like 0.8 will make the output more random, while lower values like 0.2 will make it more focused and deterministic. [default: 0.7]
+        #[arg(short, long)]
+        temperature: Option<f32>,
 
-        /// An alternative to sampling with temperature, called nucleus sampling, where the model considers the results of the tokens with top_p probability mass. So 0.1 means only the tokens comprising the top 10% probability mass are considered. (Optional)
-        #[arg(short, long, default_value_t = 1.0)]
-        top_p: f32,
+        /// An alternative to sampling with temperature, called nucleus sampling, where the model considers the results of the tokens with top_p probability mass. So 0.1 means only the tokens comprising the top 10% probability mass are considered. [default: 1.0]
+        #[arg(short = 'p', long)]
+        top_p: Option<f32>,
+
+        /// System prompt sent before the conversation
+        #[arg(long, conflicts_with = "system_file")]
+        system: Option<String>,
+
+        /// Read the system prompt from a file
+        #[arg(long)]
+        system_file: Option<PathBuf>,
+
+        /// Use a named preset from the config file, flags given on the command line still take precedence
+        #[arg(long)]
+        preset: Option<String>,
+
+        /// Keep the conversation in the named session, replaying its earlier turns as context
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Also write the full response to this file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
     #[command(visible_alias = "l", about = "List all available models")]
-    Models,
+    Models {
+        /// Only show models whose id contains this text (case-insensitive)
+        #[arg(short, long)]
+        filter: Option<String>,
+
+        /// Print the list as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
 
     #[command(visible_alias = "p", about = "Pin a specific model")]
     Pin {
@@ -68,6 +97,132 @@ enum Commands {
         #[arg(value_name = "MODEL")]
         model: String,
     },
+
+    #[command(visible_alias = "s", about = "Manage saved chat sessions")]
+    Session {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionCommands {
+    /// List saved sessions
+    List,
+    /// Delete the history of a session
+    Clear {
+        /// The name of the session
+        name: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Role {
+    User,
+    Assistant,
+}
+
+/// A model as printed by `models --json`
+#[derive(Serialize)]
+struct ModelEntry<'a> {
+    /// Position in the full list, usable with `pin`
+    index: usize,
+    id: &'a str,
+    owned_by: &'a str,
+    created: String,
+}
+
+/// One message of a saved session
+#[derive(Serialize, Deserialize)]
+struct Turn {
+    role: Role,
+    content: String,
+}
+
+/// Sessions are stored as `<name>.json` in a `sessions` directory next to the config file
+fn session_dir(config_path: &str) -> PathBuf {
+    Path::new(config_path)
+        .parent()
+        .expect("Failed to get parent directory of config file")
+        .join("sessions")
+}
+
+fn session_path(config_path: &str, name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid session name '{}', use letters, digits, '-' and '_' only", name).into());
+    }
+    Ok(session_dir(config_path).join(format!("{}.json", name)))
+}
+
+fn load_session(path: &Path) -> Result<Vec<Turn>, Box<dyn std::error::Error>> {
+    match read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn save_session(path: &Path, turns: &[Turn]) -> Result<(), Box<dyn std::error::Error>> {
+    create_dir_all(path.parent().expect("Failed to get session directory"))?;
+    write(path, serde_json::to_string_pretty(turns)?)?;
+    Ok(())
+}
+
+fn to_request_message(turn: &Turn) -> ChatCompletionRequestMessage {
+    match turn.role {
+        Role::User => ChatCompletionRequestUserMessageArgs::default()
+            .content(turn.content.as_str())
+            .build()
+            .expect("Failed to build user message")
+            .into(),
+        Role::Assistant => ChatCompletionRequestAssistantMessageArgs::default()
+            .content(turn.content.as_str())
+            .build()
+            .expect("Failed to build assistant message")
+            .into(),
+    }
+}
+
+fn handle_session_command(config_path: &str, command: &SessionCommands) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        SessionCommands::List => {
+            let mut sessions: Vec<(String, usize)> = match read_dir(session_dir(config_path)) {
+                Ok(entries) => entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                    .map(|p| {
+                        let turns = load_session(&p).map(|t| t.len()).unwrap_or(0);
+                        (p.file_stem().unwrap_or_default().to_string_lossy().into_owned(), turns)
+                    })
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            if sessions.is_empty() {
+                println!("No saved sessions");
+                return Ok(());
+            }
+            sessions.sort();
+
+            let mut table = Table::default();
+            table.add_row(row!["Session", "Messages"]);
+            sessions.iter().for_each(|(name, turns)| {
+                table.add_row(row![name, turns]);
+            });
+            table.printstd();
+        }
+        SessionCommands::Clear { name } => {
+            let path = session_path(config_path, name)?;
+            if path.exists() {
+                remove_file(&path)?;
+                println!("Cleared session: {}", name);
+            } else {
+                println!("No such session: {}", name);
+            }
+        }
+    }
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -87,6 +242,28 @@ struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
 
+    /// Named sets of request parameters, selected with `--preset`. (Optional)
+    /// Example:
+    /// [presets.code]
+    /// model = "gpt-4o"
+    /// temperature = 0.2
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, Preset>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct Preset {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
 }
 
 
@@ -104,6 +281,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         conf
     };
 
+    // Sessions are local files, no client needed
+    if let Commands::Session { command } = &cli.command {
+        return handle_session_command(&config_path, command);
+    }
 
     let client = Client::with_config(OpenAIConfig::default()
         .with_api_key(cli.key.or(config.api_key.take()).ok_or("API key is required")?)
@@ -111,22 +292,58 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
 
     match &cli.command {
-        Commands::Chat { prompt, max_tokens, temperature, top_p } => {
-            let request = CreateChatCompletionRequestArgs::default()
-                .model(cli.model.or(config.model.take()).ok_or("Model is required")?.as_str())
-                .max_tokens(*max_tokens)
-                .temperature(*temperature)
-                .top_p(*top_p)
-                .messages([ChatCompletionRequestUserMessageArgs::default()
-                    .content(prompt.join(" ").as_str())
+        Commands::Chat { prompt, max_tokens, temperature, top_p, system, system_file, preset, session, output } => {
+            let preset = match preset {
+                Some(name) => config.presets.get(name).cloned().ok_or_else(|| {
+                    format!("Unknown preset '{}', available: {}", name, config.presets.keys().cloned().collect::<Vec<_>>().join(", "))
+                })?,
+                None => Preset::default(),
+            };
+            let system = match system_file {
+                Some(path) => Some(read_to_string(path)?.trim_end().to_string()),
+                None => system.clone(),
+            };
+
+            let prompt = if prompt.is_empty() {
+                let mut input = String::new();
+                stdin().read_to_string(&mut input)?;
+                input.trim().to_string()
+            } else {
+                prompt.join(" ")
+            };
+            if prompt.is_empty() {
+                return Err("Prompt is required, pass it as arguments or through stdin".into());
+            }
+
+            let session_file = session.as_deref().map(|name| session_path(&config_path, name)).transpose()?;
+            let mut history = match &session_file {
+                Some(path) => load_session(path)?,
+                None => Vec::new(),
+            };
+            history.push(Turn { role: Role::User, content: prompt });
+
+            let mut messages: Vec<ChatCompletionRequestMessage> = Vec::new();
+            if let Some(system) = &system {
+                messages.push(ChatCompletionRequestSystemMessageArgs::default()
+                    .content(system.as_str())
                     .build()
-                    .expect("Failed to build user message")
-                    .into()])
+                    .expect("Failed to build system message")
+                    .into());
+            }
+            messages.extend(history.iter().map(to_request_message));
+
+            let request = CreateChatCompletionRequestArgs::default()
+                .model(cli.model.or(preset.model).or(config.model.take()).ok_or("Model is required")?.as_str())
+                .max_tokens(max_tokens.or(preset.max_tokens).unwrap_or(1000))
+                .temperature(temperature.or(preset.temperature).unwrap_or(0.7))
+                .top_p(top_p.or(preset.top_p).unwrap_or(1.0))
+                .messages(messages)
                 .build()
                 .expect("Failed to build request");
 
             let mut stream = client.chat().create_stream(request).await.expect("Failed to create stream");
 
+            let mut reply = String::new();
             let mut lock = stdout().lock();
             while let Some(result) = stream.next().await {
                 match result {
@@ -134,6 +351,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         response.choices.iter().for_each(|chat_choice| {
                             if let Some(ref content) = chat_choice.delta.content {
                                 write!(lock, "{}", content).unwrap();
+                                reply.push_str(content);
                             }
                         });
                     }
@@ -143,25 +361,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 stdout().flush()?;
             }
+
+            if let Some(path) = output {
+                write(path, &reply)?;
+            }
+
+            // Only keep turns that actually got an answer
+            if let (Some(path), false) = (&session_file, reply.is_empty()) {
+                history.push(Turn { role: Role::Assistant, content: reply });
+                save_session(path, &history)?;
+            }
         }
-        Commands::Models => {
+        Commands::Models { filter, json } => {
             let mut models = client.models().list().await.expect("Failed to list models");
 
 
             // 按创建时间排序
+            models.data.sort_by_key(|m| -(m.created as i32));
+
+            // 过滤时保留原始序号，便于 pin 使用
+            let filter = filter.as_ref().map(|f| f.to_lowercase());
+            let entries: Vec<ModelEntry> = models.data.iter().enumerate()
+                .filter(|(_, model)| filter.as_ref().is_none_or(|f| model.id.to_lowercase().contains(f)))
+                .map(|(ind, model)| {
+                    let created_time: DateTime<Utc> = DateTime::from_timestamp(model.created as i64, 0).expect("Failed to parse timestamp");
+                    ModelEntry { index: ind, id: &model.id, owned_by: &model.owned_by, created: created_time.format("%Y-%m-%d").to_string() }
+                })
+                .collect();
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+                return Ok(());
+            }
 
             // 创建表格
             let mut table = Table::default();
             table.add_row(row!["Index", "ID", "Owned By", "Created"]);
-
-            models.data.sort_by_key(|m| -(m.created as i32));
-            models.data.iter().enumerate()
-                .for_each(
-                    |(ind, model)| {
-                        let created_time: DateTime<Utc> = DateTime::from_timestamp(model.created as i64, 0).expect("Failed to parse timestamp");
-                        table.add_row(row![ind, model.id, model.owned_by, created_time.format("%Y-%m-%d")]);
-                    }
-                );
+            entries.iter()
+                .for_each(|entry| {
+                    table.add_row(row![entry.index, entry.id, entry.owned_by, entry.created]);
+                });
 
             // 打印表格
             table.printstd();
@@ -178,15 +417,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             } else { model.clone() };
 
 
-            Process::new("setx")
-                .arg("OPENAI_MODEL")
-                .arg(&model_name)
-                .spawn()
-                .expect("Failed to execute command")
-                .wait()
-                .expect("Failed to wait");
+            if cfg!(windows) {
+                Process::new("setx")
+                    .arg("OPENAI_MODEL")
+                    .arg(&model_name)
+                    .spawn()
+                    .expect("Failed to execute command")
+                    .wait()
+                    .expect("Failed to wait");
+            } else {
+                // 重新读取配置文件，上面构建客户端时已取走了 key 和 host
+                let mut stored: Config = toml::from_str(&read_to_string(&config_path)?)?;
+                stored.model = Some(model_name.clone());
+                write(&config_path, toml::to_string(&stored)?)?;
+                println!("Saved model to {}", config_path);
+                if std::env::var_os("OPENAI_MODEL").is_some() {
+                    println!("OPENAI_MODEL is set and takes precedence over the config file, update it with:");
+                }
+                println!("export OPENAI_MODEL='{}'", model_name);
+            }
             println!("Pinned model: {}", model_name);
         }
+        Commands::Session { .. } => unreachable!("session commands are handled before creating the client"),
     }
 
     Ok(())
@@ -5,12 +5,14 @@ use dialoguer::theme::ColorfulTheme;
 use dialoguer::Input;
 use rand::Rng;
 use rayon::prelude::*;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::PathBuf;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command as StdProcessCommand;
 use std::time::Duration;
-use std::{fs, io, thread, vec};
+use std::{env, fs, io, thread, vec};
 
 trait GetColumn {
     #[allow(unused)]
@@ -59,7 +61,8 @@ where
 
 
 fn extract_field(file_paths: &[PathBuf], extract_fields: Vec<String>) -> Vec<Vec<String>> {
-    file_paths.iter().par_bridge()
+    // par_iter keeps the file order, so "first seen" below is well defined
+    file_paths.par_iter()
         .filter(|&p| p.exists())
         .map(|path| File::open(path).expect("Failed to open file"))
         .map(|file| ReaderBuilder::new().has_headers(true).from_reader(BufReader::new(file)))
@@ -68,6 +71,109 @@ fn extract_field(file_paths: &[PathBuf], extract_fields: Vec<String>) -> Vec<Vec
         }).flatten().collect()
 }
 
+/// BBDown options for a single download.
+#[derive(Clone)]
+struct DownloadFlags {
+    work_dir: String,
+    video_only: bool,
+    audio_only: bool,
+    sub_only: bool,
+    cover_only: bool,
+    skip_sub: bool,
+    skip_cover: bool,
+}
+
+impl DownloadFlags {
+    fn to_args(&self) -> Vec<&str> {
+        vec![
+            Some("--work-dir"),
+            Some(self.work_dir.as_str()),
+            self.video_only.then_some("--video-only"),
+            self.audio_only.then_some("--audio-only"),
+            self.sub_only.then_some("--sub-only"),
+            self.cover_only.then_some("--cover-only"),
+            self.skip_sub.then_some("--skip-subtitle"),
+            self.skip_cover.then_some("--skip-cover"),
+        ]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
+
+/// One video to download. Options left unset fall back to the command line flags.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DownloadEntry {
+    url: String,
+    title: Option<String>,
+    work_dir: Option<String>,
+    video_only: Option<bool>,
+    audio_only: Option<bool>,
+    sub_only: Option<bool>,
+    cover_only: Option<bool>,
+    skip_sub: Option<bool>,
+    skip_cover: Option<bool>,
+}
+
+impl DownloadEntry {
+    /// Builds an entry from a CSV row of `[url, title]`.
+    fn from_row(row: Vec<String>) -> Option<Self> {
+        let mut fields = row.into_iter();
+        Some(DownloadEntry {
+            url: fields.next()?,
+            title: fields.next(),
+            work_dir: None,
+            video_only: None,
+            audio_only: None,
+            sub_only: None,
+            cover_only: None,
+            skip_sub: None,
+            skip_cover: None,
+        })
+    }
+
+    fn flags(&self, defaults: &DownloadFlags) -> DownloadFlags {
+        DownloadFlags {
+            work_dir: self.work_dir.clone().unwrap_or_else(|| defaults.work_dir.clone()),
+            video_only: self.video_only.unwrap_or(defaults.video_only),
+            audio_only: self.audio_only.unwrap_or(defaults.audio_only),
+            sub_only: self.sub_only.unwrap_or(defaults.sub_only),
+            cover_only: self.cover_only.unwrap_or(defaults.cover_only),
+            skip_sub: self.skip_sub.unwrap_or(defaults.skip_sub),
+            skip_cover: self.skip_cover.unwrap_or(defaults.skip_cover),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchConfig {
+    #[serde(rename = "download", default)]
+    downloads: Vec<DownloadEntry>,
+}
+
+/// Reads the `[[download]]` entries of a batch file, parsed as YAML for `.yaml`/`.yml`
+/// and as TOML otherwise.
+fn read_config(path: &Path) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let config: BatchConfig = match path.extension().and_then(|e| e.to_str()) {
+        Some("yaml" | "yml") => serde_yaml::from_str(&content)?,
+        _ => toml::from_str(&content)?,
+    };
+    Ok(config.downloads)
+}
+
+/// Drops entries whose URL was already seen, keeping the first one.
+/// Returns the number of removed duplicates.
+fn dedup_by_url(entries: &mut Vec<DownloadEntry>) -> usize {
+    let before = entries.len();
+    let mut seen: HashSet<String> = HashSet::new();
+    entries.retain(|entry| seen.insert(entry.url.clone()));
+    before - entries.len()
+}
+
 
 fn main() {
     // Set the number of threads to the number of CPU cores
@@ -77,83 +183,202 @@ fn main() {
     let matches = app.get_matches();
 
 
-    let file_paths: Vec<PathBuf> = matches.get_many::<String>("file_paths").unwrap().cloned().map(PathBuf::from).collect();
-    let video_only = matches.get_flag("video_only");
-    let audio_only = matches.get_flag("audio_only");
-    let sub_only = matches.get_flag("sub_only");
-    let cover_only = matches.get_flag("cover_only");
-    let skip_sub = matches.get_flag("skip_sub");
-    let skip_cover = matches.get_flag("skip_cover");
-    let work_dir: String = matches.get_one::<String>("work_dir").unwrap().to_string();
+    let file_paths: Vec<PathBuf> = matches.get_many::<String>("file_paths")
+        .into_iter()
+        .flatten()
+        .map(PathBuf::from)
+        .collect();
+    let defaults = DownloadFlags {
+        work_dir: matches.get_one::<String>("work_dir").unwrap().to_string(),
+        video_only: matches.get_flag("video_only"),
+        audio_only: matches.get_flag("audio_only"),
+        sub_only: matches.get_flag("sub_only"),
+        cover_only: matches.get_flag("cover_only"),
+        skip_sub: matches.get_flag("skip_sub"),
+        skip_cover: matches.get_flag("skip_cover"),
+    };
     let interval: u64 = matches.get_one::<String>("interval").unwrap().parse::<u64>().unwrap();
     let url_tab = matches.get_one::<String>("url_tab").unwrap().to_owned();
     let title_tab = matches.get_one::<String>("title_tab").unwrap().to_owned();
     let clean_up = matches.get_flag("clean_failures");
+    let bbdown = match resolve_executable(matches.get_one::<String>("bbdown_path").unwrap()) {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", format!(
+                "BBDown executable '{}' not found. Install it on PATH, or pass --bbdown-path / set BBDOWN_PATH to its location.",
+                matches.get_one::<String>("bbdown_path").unwrap()
+            ).red());
+            std::process::exit(1);
+        }
+    };
+    let retries: u32 = matches.get_one::<String>("retries").unwrap().parse::<u32>().expect("Retries must be a non-negative integer");
+
+    let log_path: PathBuf = matches.get_one::<String>("log").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(&defaults.work_dir).join(".avd_done"));
+    let done_urls = read_done_log(&log_path);
+    if !done_urls.is_empty() {
+        println!("Loaded {} completed URL(s) from {}", done_urls.len(), log_path.display());
+    }
 
-    let data_rows = extract_field(&file_paths, vec![url_tab, title_tab]);
+    let mut data_rows: Vec<DownloadEntry> = extract_field(&file_paths, vec![url_tab, title_tab])
+        .into_iter()
+        .filter_map(DownloadEntry::from_row)
+        .collect();
+    if let Some(config_path) = matches.get_one::<String>("config") {
+        match read_config(Path::new(config_path)) {
+            Ok(entries) => data_rows.extend(entries),
+            Err(e) => {
+                eprintln!("{}", format!("Failed to read config '{config_path}': {e}").red());
+                std::process::exit(1);
+            }
+        }
+    }
+    let duplicates = dedup_by_url(&mut data_rows);
+    if duplicates > 0 {
+        println!("{}", format!("Removed {} duplicate URL(s)", duplicates).yellow());
+    }
     // 使用标准库 io::stdin 和 io::stdout 实现 prompt 输入
 
     println!("Total URLs: {}", data_rows.len());
 
-    let start: usize = Input::with_theme(&ColorfulTheme::default())
+    let parse_index = |name: &str| {
+        matches.get_one::<String>(name).map(|v| v.parse::<usize>().unwrap_or_else(|_| {
+            eprintln!("{}", format!("--{name} must be a non-negative integer, got '{v}'").red());
+            std::process::exit(1);
+        }))
+    };
+
+    let start: usize = parse_index("start").unwrap_or_else(|| Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Enter the starting index")
         .default(0)
         .interact()
-        .expect("Failed to read input");
+        .expect("Failed to read input"));
 
-    let end: usize = Input::with_theme(&ColorfulTheme::default())
+    let end: usize = parse_index("end").unwrap_or_else(|| Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Enter the ending index")
         .default(data_rows.len())
         .interact()
         .expect("Failed to read input")
-        .min(data_rows.len());
-
-
-    let options: Vec<&str> = vec![
-        Some("--work-dir"),
-        Some(work_dir.as_str()),
-        video_only.then_some("--video-only"),
-        audio_only.then_some("--audio-only"),
-        sub_only.then_some("--sub-only"),
-        cover_only.then_some("--cover-only"),
-        skip_sub.then_some("--skip-subtitle"),
-        skip_cover.then_some("--skip-cover"),
-    ]
-        .into_iter()
-        .flatten()
-        .filter(|s| !s.is_empty())
-        .collect();
+        .min(data_rows.len()));
+
+    if start > end || end > data_rows.len() {
+        eprintln!("{}", format!("Invalid range {start}..{end}: expected start <= end <= {}", data_rows.len()).red());
+        std::process::exit(1);
+    }
 
     let mut rng = rand::thread_rng();
+    let mut failed_urls: Vec<String> = Vec::new();
 
 
     let download_count = (start..end)
         .map(|ind| (ind, &data_rows[ind]))
-        .map(|(ind, row)| (ind, &row[0], &row[1]))
-        .filter_map(|(ind, url, title)| {
+        .filter_map(|(ind, entry)| {
+            let url = &entry.url;
+            let title = entry.title.as_deref().unwrap_or(url);
+            let flags = entry.flags(&defaults);
             println!("{}", format!("Checking [{}/{}]:{title} | {url}", ind + 1, data_rows.len()).cyan());
-            if PathBuf::from(format!("{}/{}", work_dir, title)).exists()
-                || PathBuf::from(format!("{}/{}.mp4", work_dir, title)).exists() {
+            if done_urls.contains(url.as_str()) {
+                println!("{}", format!("Already in {}, skipping: {title}", log_path.display()).yellow());
+                None
+            } else if entry.title.is_some()
+                && (PathBuf::from(format!("{}/{}", flags.work_dir, title)).exists()
+                || PathBuf::from(format!("{}/{}.mp4", flags.work_dir, title)).exists()) {
                 println!("{}", format!("File already exists, skipping: {title}").yellow());
                 None
-            } else { Some(url) }
+            } else { Some((url, flags)) }
         })
-        .filter(|url| {
-            let _success = StdProcessCommand::new("bbdown")
-                .args(&options)
-                .arg(url)
-                .status()
-                .expect("Failed to get exit status")
-                .success();
-
-            thread::sleep(Duration::from_secs(rng.gen_range(interval / 2..=interval / 3 * 2)));
-            _success
+        .filter(|(url, flags)| {
+            for attempt in 0..=retries {
+                if attempt > 0 {
+                    // 指数退避: 随机间隔 * 2^attempt
+                    let backoff = backoff_interval(&mut rng, interval, attempt);
+                    println!("{}", format!("Retrying [{attempt}/{retries}] in {}s: {url}", backoff.as_secs()).yellow());
+                    thread::sleep(backoff);
+                }
+
+                let success = StdProcessCommand::new(&bbdown)
+                    .args(flags.to_args())
+                    .arg(url)
+                    .status()
+                    .expect("Failed to get exit status")
+                    .success();
+
+                thread::sleep(random_interval(&mut rng, interval));
+                if success {
+                    append_done_log(&log_path, url);
+                    return true;
+                }
+            }
+            failed_urls.push(url.to_string());
+            false
         }).count();
 
     println!("{}", format!("Downloaded [{}/{}] files successfully.", download_count, data_rows.len()).blue());
+    if !failed_urls.is_empty() {
+        println!("{}", format!("Failed to download {} URL(s) after {} retries:", failed_urls.len(), retries).red());
+        for url in &failed_urls {
+            println!("  {}", url);
+        }
+    }
     if clean_up {
-        delete_numeric_dirs(&PathBuf::from(work_dir));
+        let work_dirs: HashSet<String> = data_rows[start..end]
+            .iter()
+            .map(|entry| entry.flags(&defaults).work_dir)
+            .collect();
+        for work_dir in work_dirs {
+            delete_numeric_dirs(&PathBuf::from(work_dir));
+        }
+    }
+}
+
+/// Resolves a bare executable name against PATH, or checks that an explicit path exists.
+fn resolve_executable(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    if path.components().count() > 1 || path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
     }
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths).find_map(|dir| {
+            [program.to_string(), format!("{program}.exe")]
+                .into_iter()
+                .map(|name| dir.join(name))
+                .find(|candidate| candidate.is_file())
+        })
+    })
+}
+
+/// Reads the URLs recorded as completed by previous runs.
+fn read_done_log(log_path: &PathBuf) -> HashSet<String> {
+    fs::read_to_string(log_path)
+        .map(|content| content.lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+fn append_done_log(log_path: &PathBuf, url: &str) {
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .and_then(|mut file| writeln!(file, "{}", url));
+    if let Err(e) = result {
+        eprintln!("{}", format!("Failed to record {url} in {}: {e}", log_path.display()).red());
+    }
+}
+
+fn random_interval(rng: &mut impl Rng, interval: u64) -> Duration {
+    Duration::from_secs(rng.gen_range(interval / 2..=interval / 3 * 2))
+}
+
+/// Highest power of two the retry backoff is scaled by.
+const MAX_BACKOFF_EXPONENT: u32 = 6;
+
+/// Upper bound of a single retry backoff.
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+/// Random interval scaled by `2^attempt`, with the exponent and the result both capped.
+fn backoff_interval(rng: &mut impl Rng, interval: u64, attempt: u32) -> Duration {
+    random_interval(rng, interval)
+        .checked_mul(2u32.saturating_pow(attempt.min(MAX_BACKOFF_EXPONENT)))
+        .map_or(MAX_BACKOFF, |backoff| backoff.min(MAX_BACKOFF))
 }
 
 fn delete_numeric_dirs(path: &PathBuf) {
@@ -179,8 +404,14 @@ fn make_app() -> Command {
         .about("Download videos from URLs listed in CSV files using BBDown.")
         .arg(Arg::new("file_paths")
             .value_name("CSV")
-            .required(true)
+            .required_unless_present("config")
+            .num_args(1..)
             .help("One or more CSV files containing URLs to download"))
+        .arg(Arg::new("config")
+            .long("config")
+            .value_name("FILE")
+            .help("TOML (or .yaml/.yml) batch file of [[download]] entries with url, title and \
+                   per-entry options such as work_dir or video_only that override the flags."))
         .arg(Arg::new("video_only")
             .short('v')
             .long("video-only")
@@ -224,6 +455,35 @@ fn make_app() -> Command {
             .default_value("5")
             .help("Interval between downloads in seconds.")
         )
+        .arg(Arg::new("retries")
+            .short('r')
+            .long("retries")
+            .value_name("N")
+            .default_value("2")
+            .help("Number of times to retry a failed download, with exponential backoff.")
+        )
+        .arg(Arg::new("bbdown_path")
+            .long("bbdown-path")
+            .value_name("PATH")
+            .env("BBDOWN_PATH")
+            .default_value("bbdown")
+            .help("Path to the BBDown executable, or a name to look up on PATH.")
+        )
+        .arg(Arg::new("start")
+            .long("start")
+            .value_name("INDEX")
+            .help("Index of the first row to download; prompts when omitted.")
+        )
+        .arg(Arg::new("end")
+            .long("end")
+            .value_name("INDEX")
+            .help("Index after the last row to download; prompts when omitted.")
+        )
+        .arg(Arg::new("log")
+            .long("log")
+            .value_name("PATH")
+            .help("Log of completed URLs, used to resume interrupted batches. Default is '<work-dir>/.avd_done'.")
+        )
         .arg(Arg::new("url_tab")
             .long("url-tab")
             .value_name("TAB")
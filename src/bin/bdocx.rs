@@ -1,5 +1,6 @@
 use clap::Parser;
 use docx_rs::*;
+use pulldown_cmark::{Event, Parser as MarkdownParser, Tag, TagEnd};
 use regex::Regex;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -16,8 +17,21 @@ struct Cli {
     /// Output file path (DOCX)
     #[arg(short, long, default_value = "./output.docx")]
     output: PathBuf,
+
+    /// Parse the input as Markdown: `#`/`##`/`###` headings and inline bold, italic and code
+    #[arg(short, long)]
+    markdown: bool,
 }
 
+/// Numbering id shared by every bullet list
+const BULLET_NUMBERING: usize = 2;
+/// Abstract numbering behind the ordered lists
+const ORDERED_ABSTRACT_NUMBERING: usize = 3;
+/// Numbering id of the first ordered list, each further list takes the next id so it restarts at 1
+const ORDERED_NUMBERING_BASE: usize = 3;
+/// Nesting levels supported by the list numberings
+const LIST_LEVELS: usize = 9;
+
 #[derive(Default)]
 struct DocBuilder {
     doc: Docx,
@@ -93,12 +107,314 @@ impl DocBuilder {
         self
     }
 
+    /// Register the bullet numbering and one restarting instance per ordered list
+    fn add_list_numbering(mut self, ordered_lists: usize) -> Self {
+        let mut bullets = AbstractNumbering::new(BULLET_NUMBERING);
+        let mut ordered = AbstractNumbering::new(ORDERED_ABSTRACT_NUMBERING);
+        for level in 0..LIST_LEVELS {
+            let indent = 420 * (level as i32 + 1);
+            bullets = bullets.add_level(
+                Level::new(
+                    level,
+                    Start::new(1),
+                    NumberFormat::new("bullet"),
+                    LevelText::new(["•", "◦", "▪"][level % 3]),
+                    LevelJc::new("left"),
+                )
+                    .indent(Some(indent), Some(SpecialIndentType::Hanging(420)), None, None),
+            );
+            ordered = ordered.add_level(
+                Level::new(
+                    level,
+                    Start::new(1),
+                    NumberFormat::new("decimal"),
+                    LevelText::new(format!("%{}.", level + 1)),
+                    LevelJc::new("left"),
+                )
+                    .indent(Some(indent), Some(SpecialIndentType::Hanging(420)), None, None),
+            );
+        }
+
+        self.doc = self.doc
+            .add_abstract_numbering(bullets)
+            .add_abstract_numbering(ordered)
+            .add_numbering(Numbering::new(BULLET_NUMBERING, BULLET_NUMBERING));
+        for list in 0..ordered_lists {
+            self.doc = self.doc.add_numbering(
+                Numbering::new(ORDERED_NUMBERING_BASE + list, ORDERED_ABSTRACT_NUMBERING)
+                    .add_override(LevelOverride::new(0).start(1)),
+            );
+        }
+        self
+    }
+
     // 提供一个构建完成的方法以返回最终的Docx对象
     fn build(self) -> Docx {
         self.doc
     }
 }
 
+/// Split a line of Markdown into runs carrying its inline bold, italic and code formatting
+fn markdown_runs(text: &str) -> Vec<Run> {
+    let mut runs = Vec::new();
+    let (mut bold, mut italic) = (0, 0);
+
+    for event in MarkdownParser::new(text) {
+        match event {
+            Event::Start(Tag::Strong) => bold += 1,
+            Event::End(TagEnd::Strong) => bold -= 1,
+            Event::Start(Tag::Emphasis) => italic += 1,
+            Event::End(TagEnd::Emphasis) => italic -= 1,
+            Event::Text(text) => runs.push(styled_run(&text, bold > 0, italic > 0)),
+            Event::Code(code) => runs.push(
+                styled_run(&code, bold > 0, italic > 0)
+                    .fonts(RunFonts::new().ascii("Consolas").hi_ansi("Consolas")),
+            ),
+            _ => {}
+        }
+    }
+    runs
+}
+
+fn styled_run(text: &str, bold: bool, italic: bool) -> Run {
+    let mut run = Run::new().add_text(text);
+    if bold {
+        run = run.bold();
+    }
+    if italic {
+        run = run.italic();
+    }
+    run
+}
+
+/// Build a paragraph from a Markdown line, mapping `#` headings to the Heading 1/2/3 styles
+fn markdown_paragraph(line: &str, re_heading: &Regex) -> Paragraph {
+    let (style, text) = match re_heading.captures(line) {
+        Some(caps) => {
+            let level = caps[1].len().min(3);
+            println!("{}{}", "  ".repeat(level - 1), &caps[2]);
+            (format!("Heading {}", level), caps.get(2).map_or("", |m| m.as_str()))
+        }
+        None => ("Main".to_string(), line),
+    };
+
+    markdown_runs(text)
+        .into_iter()
+        .fold(Paragraph::new(), |paragraph, run| paragraph.add_run(run))
+        .style(&style)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ListKind {
+    Bullet,
+    Ordered,
+}
+
+/// Turns input lines into paragraphs, keeping track of the lists spanning several lines
+struct LineConverter {
+    markdown: bool,
+    re_level_1: Regex,
+    re_level_2: Regex,
+    re_level_3: Regex,
+    re_heading: Regex,
+    re_bullet: Regex,
+    re_ordered: Regex,
+    /// Indentation and kind of each open list level
+    indents: Vec<(usize, ListKind)>,
+    /// Number of ordered lists started so far
+    ordered_lists: usize,
+    in_ordered_list: bool,
+}
+
+impl LineConverter {
+    fn new(markdown: bool) -> Result<Self, regex::Error> {
+        Ok(Self {
+            markdown,
+            // Define regular expressions for different levels
+            re_level_1: Regex::new(r"^\d+\.?\s+(.+)$")?,
+            re_level_2: Regex::new(r"^\d+(\.\d+)\.?\s+(.+)$")?,
+            re_level_3: Regex::new(r"^\d+(\.\d+){2}\.?\s+(.+)$")?,
+            re_heading: Regex::new(r"^(#{1,6})\s+(.+)$")?,
+            re_bullet: Regex::new(r"^(\s*)[-*]\s+(.+)$")?,
+            re_ordered: Regex::new(r"^(\s*)\d+\.\s+(.+)$")?,
+            indents: Vec::new(),
+            ordered_lists: 0,
+            in_ordered_list: false,
+        })
+    }
+
+    fn convert(&mut self, line: &str) -> Paragraph {
+        if let Some(paragraph) = self.list_paragraph(line) {
+            return paragraph;
+        }
+        self.end_list();
+
+        if self.markdown {
+            markdown_paragraph(line, &self.re_heading)
+        } else {
+            self.text_paragraph(line)
+        }
+    }
+
+    fn end_list(&mut self) {
+        self.indents.clear();
+        self.in_ordered_list = false;
+    }
+
+    /// Detect a `- `/`* ` or `1. ` list item, nested by its indentation.
+    ///
+    /// Without `--markdown` an unindented `1. ` line stays a numbered heading,
+    /// unless it continues an open ordered list.
+    fn list_paragraph(&mut self, line: &str) -> Option<Paragraph> {
+        let continues_ordered = self
+            .indents
+            .first()
+            .is_some_and(|&(_, kind)| kind == ListKind::Ordered);
+        let (kind, caps) = match (self.re_bullet.captures(line), self.re_ordered.captures(line)) {
+            (Some(caps), _) => (ListKind::Bullet, caps),
+            (_, Some(caps)) if self.markdown || !caps[1].is_empty() || continues_ordered => {
+                (ListKind::Ordered, caps)
+            }
+            _ => return None,
+        };
+
+        let indent = caps[1].replace('\t', "    ").len();
+        while self.indents.last().is_some_and(|&(open, _)| indent < open) {
+            self.indents.pop();
+        }
+        match self.indents.last_mut() {
+            Some((open, open_kind)) if indent == *open => *open_kind = kind,
+            _ => self.indents.push((indent, kind)),
+        }
+        let level = (self.indents.len() - 1).min(LIST_LEVELS - 1);
+
+        let numbering = match kind {
+            ListKind::Bullet => BULLET_NUMBERING,
+            ListKind::Ordered => {
+                if !self.in_ordered_list {
+                    self.in_ordered_list = true;
+                    self.ordered_lists += 1;
+                }
+                ORDERED_NUMBERING_BASE + self.ordered_lists - 1
+            }
+        };
+
+        let runs = if self.markdown {
+            markdown_runs(&caps[2])
+        } else {
+            vec![Run::new().add_text(&caps[2])]
+        };
+        Some(
+            runs.into_iter()
+                .fold(Paragraph::new(), |paragraph, run| paragraph.add_run(run))
+                .numbering(NumberingId::new(numbering), IndentLevel::new(level))
+                .style("Main"),
+        )
+    }
+
+    fn text_paragraph(&self, line: &str) -> Paragraph {
+        match (
+            self.re_level_1.captures(line),
+            self.re_level_2.captures(line),
+            self.re_level_3.captures(line),
+        ) {
+            (Some(caps), _, _) => {
+                println!("{}", &caps[0]);
+                Paragraph::new()
+                    .add_run(Run::new().add_text(&caps[1]))
+                    .numbering(NumberingId::new(1), IndentLevel::new(0))
+                    .style("Heading 1")
+            }
+            (_, Some(caps), _) => {
+                println!("  {}", &caps[0]);
+                Paragraph::new()
+                    .add_run(Run::new().add_text(&caps[2]))
+                    .numbering(NumberingId::new(1), IndentLevel::new(1))
+                    .style("Heading 2")
+            }
+            (_, _, Some(caps)) => {
+                println!("    {}", &caps[0]);
+                Paragraph::new()
+                    .add_run(Run::new().add_text(&caps[3]))
+                    .numbering(NumberingId::new(1), IndentLevel::new(2))
+                    .style("Heading 3")
+            }
+            _ => {
+                // Add non-title lines as regular paragraphs
+                Paragraph::new()
+                    .add_run(Run::new().add_text(line))
+                    .style("Main")
+            }
+        }
+    }
+}
+
+/// A top-level element of the document body, kept in input order
+enum Block {
+    Paragraph(Box<Paragraph>),
+    Table(Box<Table>),
+}
+
+/// Build a table from tab-separated rows, the first one being the bold header
+fn build_table(rows: &[Vec<String>]) -> Table {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let table_rows = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let cells = (0..columns)
+                .map(|column| {
+                    let text = row.get(column).map_or("", String::as_str);
+                    let run = Run::new().add_text(text);
+                    let run = if index == 0 { run.bold() } else { run };
+                    TableCell::new().add_paragraph(Paragraph::new().add_run(run))
+                })
+                .collect();
+            TableRow::new(cells)
+        })
+        .collect();
+    Table::new(table_rows)
+}
+
+/// Convert the input lines into document blocks.
+///
+/// Lines between `<table>` and `</table>` are collected as tab-separated rows and become one table.
+fn convert_lines(
+    lines: impl Iterator<Item = String>,
+    converter: &mut LineConverter,
+) -> Result<Vec<Block>, String> {
+    let mut blocks = Vec::new();
+    let mut table_rows: Option<Vec<Vec<String>>> = None;
+
+    for line in lines.filter(|line| !line.is_empty()) {
+        match (line.trim(), table_rows.as_mut()) {
+            ("<table>", None) => {
+                converter.end_list();
+                table_rows = Some(Vec::new());
+            }
+            ("<table>", Some(_)) => {
+                return Err("Nested <table> blocks are not supported".to_string());
+            }
+            ("</table>", Some(rows)) => {
+                blocks.push(Block::Table(Box::new(build_table(rows))));
+                table_rows = None;
+            }
+            ("</table>", None) => {
+                return Err("Found </table> without a matching <table>".to_string());
+            }
+            (_, Some(rows)) => {
+                rows.push(line.split('\t').map(|cell| cell.trim().to_string()).collect());
+            }
+            (_, None) => blocks.push(Block::Paragraph(Box::new(converter.convert(&line)))),
+        }
+    }
+
+    if table_rows.is_some() {
+        return Err("Unclosed <table> block at the end of the input".to_string());
+    }
+    Ok(blocks)
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
@@ -106,65 +422,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let txt_file = File::open(&cli.input).expect("Failed to open input file");
     let reader = BufReader::new(txt_file);
 
-    // Define regular expressions for different levels
-    let re_level_1 = Regex::new(r"^\d+\.?\s+(.+)$")?;
-    let re_level_2 = Regex::new(r"^\d+(\.\d+)\.?\s+(.+)$")?;
-    let re_level_3 = Regex::new(r"^\d+(\.\d+){2}\.?\s+(.+)$")?;
+    let mut converter = LineConverter::new(cli.markdown)?;
 
+    // Process each line in the input file
+    let blocks = convert_lines(reader.lines().map_while(Result::ok), &mut converter)?;
 
     // Create a new DOCX document and add abstract numbering definitions
     let mut doc = DocBuilder::default()
         .add_style()
         .add_numbering()
+        .add_list_numbering(converter.ordered_lists)
         .build();
 
-
-    // Process each line in the input file
-    let paragraphs: Vec<Paragraph> = reader
-        .lines()
-        .map_while(Result::ok)
-        .filter(|line| !line.is_empty())
-        .map(|line| {
-            match (
-                re_level_1.captures(&line),
-                re_level_2.captures(&line),
-                re_level_3.captures(&line),
-            ) {
-                (Some(caps), _, _) => {
-                    println!("{}", &caps[0]);
-                    Paragraph::new()
-                        .add_run(Run::new().add_text(&caps[1]))
-                        .numbering(NumberingId::new(1), IndentLevel::new(0))
-                        .style("Heading 1")
-                }
-                (_, Some(caps), _) => {
-                    println!("  {}", &caps[0]);
-                    Paragraph::new()
-                        .add_run(Run::new().add_text(&caps[2]))
-                        .numbering(NumberingId::new(1), IndentLevel::new(1))
-                        .style("Heading 2")
-                }
-                (_, _, Some(caps)) => {
-                    println!("    {}", &caps[0]);
-                    Paragraph::new()
-                        .add_run(Run::new().add_text(&caps[3]))
-                        .numbering(NumberingId::new(1), IndentLevel::new(2))
-                        .style("Heading 3")
-                }
-                _ => {
-                    // Add non-title lines as regular paragraphs
-                    Paragraph::new()
-                        .add_run(Run::new().add_text(line))
-                        .style("Main")
-                }
-            }
-        })
-        .collect();
-
-    for paragraph in paragraphs {
-        doc = doc.add_paragraph(paragraph);
+    for block in blocks {
+        doc = match block {
+            Block::Paragraph(paragraph) => doc.add_paragraph(*paragraph),
+            Block::Table(table) => doc.add_table(*table),
+        };
     }
-
     doc.build()
         .pack(File::create(&cli.output).expect("Failed to create output file"))
         .expect("Failed to save DOCX file");
@@ -172,3 +447,117 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Conversion successful! Output saved to {:?}", cli.output);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbering_of(paragraph: &Paragraph) -> Option<(usize, usize)> {
+        paragraph
+            .property
+            .numbering_property
+            .as_ref()
+            .and_then(|n| Some((n.id.as_ref()?.id, n.level.as_ref()?.val)))
+    }
+
+    #[test]
+    fn test_mixed_list_numbering_ids() {
+        let mut converter = LineConverter::new(false).unwrap();
+        let lines = [
+            "- fruit",
+            "  - apple",
+            "    1. granny smith",
+            "    2. fuji",
+            "  * pear",
+            "- vegetables",
+            "a plain paragraph",
+            "  1. first again",
+            "      - nested deeper",
+        ];
+        let numbering: Vec<_> = lines
+            .iter()
+            .map(|line| numbering_of(&converter.convert(line)))
+            .collect();
+
+        assert_eq!(
+            numbering,
+            vec![
+                Some((BULLET_NUMBERING, 0)),
+                Some((BULLET_NUMBERING, 1)),
+                Some((ORDERED_NUMBERING_BASE, 2)),
+                Some((ORDERED_NUMBERING_BASE, 2)),
+                Some((BULLET_NUMBERING, 1)),
+                Some((BULLET_NUMBERING, 0)),
+                None,
+                Some((ORDERED_NUMBERING_BASE + 1, 0)),
+                Some((BULLET_NUMBERING, 1)),
+            ]
+        );
+        assert_eq!(converter.ordered_lists, 2);
+    }
+
+    #[test]
+    fn test_unindented_number_stays_heading_without_markdown() {
+        let mut converter = LineConverter::new(false).unwrap();
+        assert_eq!(numbering_of(&converter.convert("1. Introduction")), Some((1, 0)));
+
+        let mut converter = LineConverter::new(true).unwrap();
+        assert_eq!(
+            numbering_of(&converter.convert("1. Introduction")),
+            Some((ORDERED_NUMBERING_BASE, 0))
+        );
+    }
+
+    #[test]
+    fn test_heading_after_bullet_list_without_markdown() {
+        let mut converter = LineConverter::new(false).unwrap();
+        let numbering: Vec<_> = ["- apple", "- pear", "2. Results", "1. first", "2. second"]
+            .iter()
+            .map(|line| numbering_of(&converter.convert(line)))
+            .collect();
+
+        assert_eq!(
+            numbering,
+            vec![
+                Some((BULLET_NUMBERING, 0)),
+                Some((BULLET_NUMBERING, 0)),
+                Some((1, 0)),
+                Some((1, 0)),
+                Some((1, 0)),
+            ]
+        );
+
+        let mut converter = LineConverter::new(false).unwrap();
+        let numbering: Vec<_> = ["  1. first", "2. second"]
+            .iter()
+            .map(|line| numbering_of(&converter.convert(line)))
+            .collect();
+        assert_eq!(
+            numbering,
+            vec![Some((ORDERED_NUMBERING_BASE, 0)), Some((ORDERED_NUMBERING_BASE, 0))]
+        );
+    }
+
+    #[test]
+    fn test_table_block_keeps_document_order() {
+        let mut converter = LineConverter::new(false).unwrap();
+        let lines = [
+            "before", "<table>", "name\tscore", "alice\t90", "bob", "</table>", "after",
+        ];
+        let blocks = convert_lines(lines.iter().map(|line| line.to_string()), &mut converter)
+            .unwrap();
+
+        assert_eq!(blocks.len(), 3);
+        assert!(matches!(blocks[0], Block::Paragraph(_)));
+        assert!(matches!(blocks[2], Block::Paragraph(_)));
+        let Block::Table(table) = &blocks[1] else {
+            panic!("expected a table");
+        };
+        assert_eq!(table.rows.len(), 3);
+        let TableChild::TableRow(last) = &table.rows[2];
+        assert_eq!(last.cells.len(), 2, "short rows are padded to the widest row");
+
+        let unclosed = ["<table>", "a"].iter().map(|line| line.to_string());
+        assert!(convert_lines(unclosed, &mut converter).is_err());
+    }
+}
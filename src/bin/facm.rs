@@ -1,13 +1,20 @@
 use clap::{Parser, Subcommand};
 use dirs::data_dir;
 use glob::glob;
+use humansize::{format_size, DECIMAL};
+use prettytable::{row, Table};
 use rayon::prelude::*;
 use regex::Regex;
+use same_file::Handle;
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(Parser)]
 /// A command line tool to manage Factorio mods by moving old versions to an 'old_mods' directory.
@@ -31,6 +38,10 @@ enum Commands {
         /// The output directory for old mods
         #[arg(short, long, default_value = get_default_old_mods_dir())]
         output_dir: PathBuf,
+
+        /// Print the moves that would be performed without touching any files
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
     },
 
     /// Export enabled mods as a zip file
@@ -78,6 +89,54 @@ enum Commands {
         /// The path to the mods directory
         #[arg(short, long, default_value = get_default_mods_dir())]
         mods_dir: PathBuf,
+
+        /// Print the mod list as JSON
+        #[arg(short, long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Find byte-identical mod zips across the mods and old_mods directories
+    #[command(alias = "d")]
+    Dedup {
+        /// The path to the mods directory
+        #[arg(short, long, default_value = get_default_mods_dir())]
+        mods_dir: PathBuf,
+
+        /// The directory holding old mods
+        #[arg(short, long, default_value = get_default_old_mods_dir())]
+        output_dir: PathBuf,
+
+        /// Replace duplicates with hard links to the first copy found
+        #[arg(short, long, default_value_t = false)]
+        link: bool,
+    },
+
+    /// Report mods whose info.json name or version disagrees with the file name
+    #[command(alias = "v")]
+    Verify {
+        /// The path to the mods directory
+        #[arg(short, long, default_value = get_default_mods_dir())]
+        mods_dir: PathBuf,
+    },
+
+    /// Check the mod portal for newer releases of the installed mods
+    #[command(alias = "u")]
+    Update {
+        /// The path to the mods directory
+        #[arg(short, long, default_value = get_default_mods_dir())]
+        mods_dir: PathBuf,
+
+        /// Download and install the available updates
+        #[arg(short, long, default_value_t = false)]
+        download: bool,
+
+        /// The Factorio account name, needed by the portal for downloads
+        #[arg(long, env = "FACTORIO_USERNAME")]
+        username: Option<String>,
+
+        /// The Factorio service token, see player-data.json or the profile page
+        #[arg(long, env = "FACTORIO_TOKEN", hide_env_values = true)]
+        token: Option<String>,
     },
 }
 
@@ -190,8 +249,10 @@ fn move_old_mods(
     mods_path: &PathBuf,
     output_dir: &PathBuf,
     latest_versions: HashMap<String, (PathBuf, u64)>,
+    dry_run: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mod_entries = get_mod_entries(mods_path)?;
+    let moved_count = AtomicUsize::new(0);
 
     mod_entries.into_par_iter().for_each(|mod_entry| {
         let entry = mod_entry.source_path.clone(); // 使用 ModEntry 中的 source_path
@@ -208,9 +269,18 @@ fn move_old_mods(
         let mut dest_path = output_dir.clone();
         dest_path.push(entry.file_name().unwrap());
 
+        if dry_run {
+            println!("Would move {} to {}", entry.display(), dest_path.display());
+            moved_count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
         // Move the file
         match fs::rename(&entry, &dest_path) {
-            Ok(_) => println!("Moved {} to {}", entry.display(), dest_path.display()),
+            Ok(_) => {
+                println!("Moved {} to {}", entry.display(), dest_path.display());
+                moved_count.fetch_add(1, Ordering::Relaxed);
+            }
             Err(e) => eprintln!(
                 "Failed to move {} to {}: {}",
                 entry.display(),
@@ -220,6 +290,191 @@ fn move_old_mods(
         }
     });
 
+    let moved_count = moved_count.into_inner();
+    if dry_run {
+        println!("{} mod(s) would be moved", moved_count);
+    } else {
+        println!("{} mod(s) moved", moved_count);
+    }
+
+    Ok(())
+}
+
+// 新增: 计算文件的 sha256 摘要
+fn hash_file(path: &PathBuf) -> Result<String, Box<dyn std::error::Error>> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// 新增: 查找内容完全相同的 mod 压缩包, 可选地用硬链接替换重复文件
+fn dedup_mods(dirs: &[&PathBuf], link: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut candidates = vec![];
+    for dir in dirs {
+        if !dir.is_dir() {
+            continue;
+        }
+        for mod_entry in get_mod_entries(dir)? {
+            candidates.push(dir.join(&mod_entry.source_path));
+        }
+    }
+
+    let hashed: Vec<(String, PathBuf, u64)> = candidates
+        .into_par_iter()
+        .filter_map(|path| {
+            let size = fs::metadata(&path).ok()?.len();
+            match hash_file(&path) {
+                Ok(hash) => Some((hash, path, size)),
+                Err(e) => {
+                    eprintln!("Failed to hash {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let mut groups: HashMap<String, Vec<(PathBuf, u64)>> = HashMap::new();
+    for (hash, path, size) in hashed {
+        groups.entry(hash).or_default().push((path, size));
+    }
+
+    let mut duplicate_groups: Vec<Vec<(PathBuf, u64)>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+    duplicate_groups.iter_mut().for_each(|group| group.sort());
+    duplicate_groups.sort();
+
+    let mut reclaimed: u64 = 0;
+    let mut reported = 0;
+    for group in &duplicate_groups {
+        let (keep, size) = &group[0];
+        // 已经是 keep 的硬链接的文件不再报告; 彼此互为硬链接的重复文件只计算一次空间
+        let Ok(keep_handle) = Handle::from_path(keep) else {
+            eprintln!("Failed to open {}", keep.display());
+            continue;
+        };
+        let mut seen = vec![];
+        let duplicates: Vec<(&PathBuf, bool)> = group[1..]
+            .iter()
+            .filter_map(|(duplicate, _)| match Handle::from_path(duplicate) {
+                Ok(handle) if handle == keep_handle => None,
+                Ok(handle) if seen.contains(&handle) => Some((duplicate, false)),
+                Ok(handle) => {
+                    seen.push(handle);
+                    Some((duplicate, true))
+                }
+                Err(_) => Some((duplicate, true)),
+            })
+            .collect();
+        if duplicates.is_empty() {
+            continue;
+        }
+
+        reported += 1;
+        println!("Identical files ({} bytes each):", size);
+        println!("  {}", keep.display());
+        for (duplicate, counted) in duplicates {
+            println!("  {}", duplicate.display());
+            if counted {
+                reclaimed += size;
+            }
+
+            if link {
+                // 先创建临时硬链接再替换, 避免中途失败丢失文件
+                let temp_path = duplicate.with_extension("zip.dedup");
+                let linked = fs::hard_link(keep, &temp_path).and_then(|_| {
+                    fs::rename(&temp_path, duplicate).inspect_err(|_| {
+                        let _ = fs::remove_file(&temp_path);
+                    })
+                });
+                match linked {
+                    Ok(()) => println!("  Linked {} to {}", duplicate.display(), keep.display()),
+                    // 例如 mods 与 old_mods 位于不同文件系统时无法硬链接, 跳过该组继续处理
+                    Err(e) => {
+                        eprintln!(
+                            "  Failed to link {} to {}: {}",
+                            duplicate.display(),
+                            keep.display(),
+                            e
+                        );
+                        if counted {
+                            reclaimed -= size;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    println!(
+        "Found {} duplicate group(s), {} {} reclaimable",
+        reported,
+        format_size(reclaimed, DECIMAL),
+        if link { "were" } else { "are" }
+    );
+
+    Ok(())
+}
+
+// 新增: 读取 mod 压缩包中顶层目录下的 info.json
+fn read_zip_info_json(zip_path: &PathBuf) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let file = fs::File::open(zip_path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+
+    let info_index = (0..archive.len())
+        .find(|&i| {
+            archive.by_index(i).is_ok_and(|f| {
+                let name = f.name().trim_start_matches('/');
+                name.matches('/').count() <= 1 && name.rsplit('/').next() == Some("info.json")
+            })
+        })
+        .ok_or("info.json not found in the zip")?;
+
+    let mut content = String::new();
+    archive.by_index(info_index)?.read_to_string(&mut content)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+// 新增: 校验 info.json 中的 name/version 与文件名是否一致
+fn verify_mods(mods_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let mod_entries = get_mod_entries(mods_path)?;
+
+    let mut mismatches: Vec<(String, String, String)> = mod_entries
+        .into_par_iter()
+        .filter_map(|mod_entry| {
+            let zip_path = mods_path.join(&mod_entry.source_path);
+            let file_name = mod_entry.source_path.to_string_lossy().to_string();
+
+            let info = match read_zip_info_json(&zip_path) {
+                Ok(info) => info,
+                Err(e) => return Some((file_name, format!("<{}>", e), String::new())),
+            };
+            let info_name = info.get("name").and_then(|v| v.as_str()).unwrap_or("");
+            let info_version = info.get("version").and_then(|v| v.as_str()).unwrap_or("");
+
+            if info_name == mod_entry.base_name && info_version == mod_entry.version {
+                return None;
+            }
+            Some((file_name, info_name.to_string(), info_version.to_string()))
+        })
+        .collect();
+
+    if mismatches.is_empty() {
+        println!("All mods match their info.json");
+        return Ok(());
+    }
+
+    mismatches.sort();
+    let mut table = Table::new();
+    table.add_row(row!["File", "info.json Name", "info.json Version"]);
+    for (file_name, info_name, info_version) in &mismatches {
+        table.add_row(row![file_name, info_name, info_version]);
+    }
+    table.printstd();
+    println!("{} mod(s) disagree with their info.json", mismatches.len());
+
     Ok(())
 }
 
@@ -253,6 +508,59 @@ fn read_mod_config(
     Ok(mod_config)
 }
 
+// 新增: 列出已安装的 mod 及其启用状态, 按名称排序
+fn list_mods(mods_path: &PathBuf, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = mods_path.join("mod-list.json");
+    let mod_config = if config_file.exists() {
+        read_mod_config(&config_file)?
+    } else {
+        HashMap::new()
+    };
+
+    let mut mod_entries = get_mod_entries(mods_path)?;
+    mod_entries.sort_by(|a, b| {
+        a.base_name
+            .cmp(&b.base_name)
+            .then_with(|| a.version.cmp(&b.version))
+    });
+
+    let is_enabled = |entry: &ModEntry| mod_config.get(&entry.base_name).copied().unwrap_or(false);
+
+    if json {
+        let mods: Vec<serde_json::Value> = mod_entries
+            .iter()
+            .map(|entry| {
+                serde_json::json!({
+                    "name": entry.base_name,
+                    "version": entry.version,
+                    "enabled": is_enabled(entry),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&mods)?);
+        return Ok(());
+    }
+
+    let name_width = mod_entries
+        .iter()
+        .map(|entry| entry.base_name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    println!("Installed mods:");
+    for entry in &mod_entries {
+        println!(
+            "[{}] {:<width$}  {}",
+            if is_enabled(entry) { "x" } else { " " },
+            entry.base_name,
+            entry.version,
+            width = name_width
+        );
+    }
+
+    Ok(())
+}
+
 fn zip_enabled_mods(
     mods_path: &PathBuf,
     output_zip: &PathBuf,
@@ -432,6 +740,93 @@ fn process_mod_folder(
     Ok(zip_file_path)
 }
 
+// 新增: 从 Content-Disposition 头中解析文件名, 参数名不区分大小写, 优先使用 RFC 6266 的 filename*
+fn filename_from_content_disposition(header: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for part in header.split(';') {
+        let Some((name, value)) = part.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename*" => extended = decode_ext_value(value.trim()),
+            "filename" => plain = Some(value.trim().trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    let file_name = extended.or(plain)?;
+
+    // 只保留文件名部分, 防止头部携带路径
+    PathBuf::from(file_name)
+        .file_name()
+        .and_then(|f| f.to_str())
+        .map(|f| f.to_string())
+}
+
+// 新增: 解码 RFC 8187 扩展值 charset'language'percent-encoded, 支持 UTF-8 和 ISO-8859-1
+fn decode_ext_value(value: &str) -> Option<String> {
+    let (charset, rest) = value.split_once('\'')?;
+    let (_language, encoded) = rest.split_once('\'')?;
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut iter = encoded.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        None
+    }
+}
+
+// 新增: 下载 mod 压缩包到 mods 目录, 返回文件路径
+// URL 的查询参数可能带有账号凭据, 错误信息中不包含它们
+fn download_mod(
+    mods_path: &Path,
+    url: reqwest::Url,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let mut shown_url = url.clone();
+    shown_url.set_query(None);
+
+    // 跟随重定向, response.url() 为重定向后的最终地址
+    let client = reqwest::blocking::Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .build()?;
+    let response = client.get(url).send().map_err(|e| e.without_url())?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to download mod from {}", shown_url).into());
+    }
+
+    // 优先使用 Content-Disposition 中的文件名, 缺失时回退到 URL 的最后一段
+    let file_name = response
+        .headers()
+        .get(reqwest::header::CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(filename_from_content_disposition)
+        .or_else(|| {
+            response
+                .url()
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .map(|segment| segment.to_string())
+        })
+        .ok_or("Invalid URL or missing file name")?;
+    let file_path = mods_path.join(&file_name);
+    let content = response.bytes().map_err(|e| e.without_url())?;
+    let mut file = fs::File::create(&file_path)?;
+    file.write_all(&content)?;
+    Ok(file_path)
+}
+
 // 修改: 重构 install_mod 函数
 fn install_mod(mods_path: &PathBuf, source: &str) -> Result<(), Box<dyn std::error::Error>> {
     // 验证 mod 来源
@@ -439,28 +834,19 @@ fn install_mod(mods_path: &PathBuf, source: &str) -> Result<(), Box<dyn std::err
 
     // 根据来源类型处理 mod
     let file_path = match mod_source.source_type {
-        ModSourceType::Url => {
-            // 下载文件
-            let response = reqwest::blocking::get(mod_source.path.to_string_lossy().as_ref())?;
-            if !response.status().is_success() {
-                return Err(format!("Failed to download mod from {}", source).into());
-            }
-
-            let file_name = response
-                .url()
-                .path_segments()
-                .and_then(|segments| segments.last())
-                .ok_or("Invalid URL or missing file name")?;
-            let file_path = mods_path.join(file_name);
-            let mut file = fs::File::create(&file_path)?;
-            let content = response.bytes()?;
-            file.write_all(&content)?;
-            file_path
-        }
+        ModSourceType::Url => download_mod(mods_path, reqwest::Url::parse(source)?)?,
         ModSourceType::LocalFile => mod_source.path.clone(),
         ModSourceType::Folder => process_mod_folder(&mod_source.path, mods_path)?,
     };
 
+    install_mod_file(mods_path, &file_path)
+}
+
+// 新增: 校验 mod 文件名并将其放入 mods 目录
+fn install_mod_file(
+    mods_path: &PathBuf,
+    file_path: &PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
     // 验证文件名是否符合 ModEntry 正则表达式
     let file_name = file_path
         .file_name()
@@ -473,7 +859,7 @@ fn install_mod(mods_path: &PathBuf, source: &str) -> Result<(), Box<dyn std::err
     // 移动文件到 mods 目录（如果不在 mods 目录中）
     if file_path.parent().unwrap() != mods_path {
         let dest_path = mods_path.join(file_name);
-        fs::rename(&file_path, &dest_path)?;
+        fs::rename(file_path, &dest_path)?;
         println!(
             "Installed {} to {}",
             file_path.display(),
@@ -486,6 +872,125 @@ fn install_mod(mods_path: &PathBuf, source: &str) -> Result<(), Box<dyn std::err
     Ok(())
 }
 
+const MOD_PORTAL_URL: &str = "https://mods.factorio.com";
+
+// 新增: mod 门户 API 返回的数据, 只保留用到的字段
+#[derive(Deserialize)]
+struct PortalMod {
+    releases: Vec<PortalRelease>,
+}
+
+#[derive(Deserialize)]
+struct PortalRelease {
+    download_url: String,
+    version: String,
+}
+
+// 新增: 查询 mod 门户中某个 mod 的最新版本及其下载路径, 门户上不存在时返回 None
+fn fetch_latest_release(
+    client: &reqwest::blocking::Client,
+    name: &str,
+) -> Result<Option<(Version, String)>, Box<dyn std::error::Error>> {
+    let mut url = reqwest::Url::parse(MOD_PORTAL_URL)?;
+    url.path_segments_mut()
+        .map_err(|_| "Invalid mod portal URL")?
+        .extend(["api", "mods", name]);
+
+    let response = client.get(url).send()?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(format!("Mod portal returned {}", response.status()).into());
+    }
+
+    let portal_mod: PortalMod = serde_json::from_str(&response.text()?)?;
+    Ok(portal_mod
+        .releases
+        .into_iter()
+        .filter_map(|release| Some((Version::parse(&release.version).ok()?, release.download_url)))
+        .max_by(|a, b| a.0.cmp(&b.0)))
+}
+
+// 新增: 检查已安装 mod 的更新, 提供账号凭据时通过 install_mod 下载安装
+fn update_mods(
+    mods_path: &PathBuf,
+    credentials: Option<(String, String)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // 每个 mod 取已安装的最高版本
+    let mut installed: HashMap<String, Version> = HashMap::new();
+    for entry in get_mod_entries(mods_path)? {
+        let Ok(version) = Version::parse(&entry.version) else {
+            continue;
+        };
+        let current = installed.entry(entry.base_name).or_insert(version.clone());
+        if version > *current {
+            *current = version;
+        }
+    }
+
+    // 并行查询门户, Box<dyn Error> 不能跨线程, 先转成字符串
+    let client = reqwest::blocking::Client::builder().build()?;
+    let mut results: Vec<_> = installed
+        .par_iter()
+        .map(|(name, version)| {
+            let latest = fetch_latest_release(&client, name).map_err(|e| e.to_string());
+            (name, version, latest)
+        })
+        .collect();
+    results.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut updates = vec![];
+    let mut failed = 0;
+    let mut table = Table::new();
+    table.add_row(row!["Mod", "Installed", "Latest"]);
+    for (name, version, latest) in results {
+        match latest {
+            Ok(Some((latest, download_url))) if latest > *version => {
+                table.add_row(row![name, version, latest]);
+                updates.push((name, download_url));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Failed to query the mod portal for {}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if updates.is_empty() {
+        println!(
+            "All {} checked mod(s) are up to date",
+            installed.len() - failed
+        );
+        return Ok(());
+    }
+    println!("{} mod(s) have updates available:", updates.len());
+    table.printstd();
+
+    let Some((username, token)) = credentials else {
+        return Ok(());
+    };
+    // 旧版本保留在 mods 目录中, 可以随后用 move 命令归档
+    for (name, download_url) in updates {
+        let installed = reqwest::Url::parse(MOD_PORTAL_URL)
+            .and_then(|base| base.join(&download_url))
+            .map_err(Into::into)
+            .and_then(|mut url| {
+                url.query_pairs_mut()
+                    .append_pair("username", &username)
+                    .append_pair("token", &token);
+                download_mod(mods_path, url)
+            })
+            .and_then(|file_path| install_mod_file(mods_path, &file_path));
+        if let Err(e) = installed {
+            eprintln!("Failed to update {}: {}", name, e);
+        }
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
@@ -493,16 +998,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Commands::Move {
             mods_dir,
             output_dir,
+            dry_run,
         } => {
             if !mods_dir.exists() || !mods_dir.is_dir() {
                 return Err("Mods directory does not exist or is not a directory".into());
             }
-            if !output_dir.exists() {
+            if !output_dir.exists() && !dry_run {
                 fs::create_dir(&output_dir)?;
             }
 
             let latest_versions = get_latest_versions(&mods_dir)?;
-            move_old_mods(&mods_dir, &output_dir, latest_versions)?;
+            move_old_mods(&mods_dir, &output_dir, latest_versions, dry_run)?;
         }
         Commands::Export {
             mods_dir,
@@ -532,18 +1038,82 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             install_mod(&mods_dir, &source)?;
         }
-        Commands::List { mods_dir } => {
+        Commands::List { mods_dir, json } => {
             if !mods_dir.exists() || !mods_dir.is_dir() {
                 return Err("Mods directory does not exist or is not a directory".into());
             }
 
-            let mod_entries = get_mod_entries(&mods_dir)?;
-            println!("Installed mods:");
-            for entry in mod_entries {
-                println!("{} (Version: {})", entry.base_name, entry.version);
+            list_mods(&mods_dir, json)?;
+        }
+        Commands::Dedup {
+            mods_dir,
+            output_dir,
+            link,
+        } => {
+            if !mods_dir.exists() || !mods_dir.is_dir() {
+                return Err("Mods directory does not exist or is not a directory".into());
+            }
+
+            dedup_mods(&[&mods_dir, &output_dir], link)?;
+        }
+        Commands::Verify { mods_dir } => {
+            if !mods_dir.exists() || !mods_dir.is_dir() {
+                return Err("Mods directory does not exist or is not a directory".into());
+            }
+
+            verify_mods(&mods_dir)?;
+        }
+        Commands::Update {
+            mods_dir,
+            download,
+            username,
+            token,
+        } => {
+            if !mods_dir.exists() || !mods_dir.is_dir() {
+                return Err("Mods directory does not exist or is not a directory".into());
             }
+
+            let credentials = match (download, username, token) {
+                (false, _, _) => None,
+                (true, Some(username), Some(token)) => Some((username, token)),
+                (true, _, _) => {
+                    return Err("--download needs --username and --token (or FACTORIO_USERNAME and FACTORIO_TOKEN)".into())
+                }
+            };
+            update_mods(&mods_dir, credentials)?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filename_from_content_disposition() {
+        assert_eq!(
+            filename_from_content_disposition("attachment; filename=\"flib_0.16.2.zip\"")
+                .as_deref(),
+            Some("flib_0.16.2.zip")
+        );
+        assert_eq!(
+            filename_from_content_disposition("attachment; Filename=flib_0.16.2.zip").as_deref(),
+            Some("flib_0.16.2.zip")
+        );
+        assert_eq!(
+            filename_from_content_disposition(
+                "attachment; filename=\"fallback.zip\"; filename*=UTF-8''%E4%B8%AD%E6%96%87_1.0.0.zip"
+            )
+            .as_deref(),
+            Some("中文_1.0.0.zip")
+        );
+        assert_eq!(
+            filename_from_content_disposition("attachment; filename=\"../../etc/evil_1.0.0.zip\"")
+                .as_deref(),
+            Some("evil_1.0.0.zip")
+        );
+        assert_eq!(filename_from_content_disposition("attachment"), None);
+    }
+}
@@ -1,15 +1,20 @@
 mod utils;
 
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::utils::setup_progress_bar;
+use crate::utils::{setup_progress_bar, OutputFormat, TableWriter};
 use clap::{Args as ClapArgs, Parser, Subcommand};
-use foxil::result::AnalysisResult;
+use foxil::result::{AnalysisResult, XfoilResult};
 use foxil::FoxConfig;
 use indicatif::ParallelProgressIterator;
 use rayon::prelude::*;
 
+const DEFAULT_CL_OUTPUT: &str = "cl_data.csv";
+const DEFAULT_SEARCH_OUTPUT: &str = "naca_search_results.csv";
+const DEFAULT_INTERPOLATE_OUTPUT: &str = "interpolated.csv";
+const DEFAULT_LOAD_OUTPUT: &str = "foil_data.csv";
+
 #[derive(Debug, Parser)]
 #[command(
     author,
@@ -38,6 +43,14 @@ struct Cli {
     )]
     polar_path: PathBuf,
 
+    /// Maximum number of XFoil processes to run at once.
+    #[arg(short, long, global = true, default_value_t = num_cpus::get(), value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    jobs: usize,
+
+    /// File format of the written tables.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -54,7 +67,11 @@ enum Commands {
     /// outputting results to a JSON file.
     SearchNaca(SearchNacaArgs),
 
+    /// Converts an XFoil polar file, or every polar file in a directory, to a CSV file.
     Load(LoadArgs),
+    /// Runs one angle of attack sweep and linearly interpolates Cl/Cd/Cm at the
+    /// requested angles, outputting the results to a CSV file.
+    Interpolate(InterpolateArgs),
 }
 
 #[derive(Debug, ClapArgs)]
@@ -82,6 +99,11 @@ struct SweepArgs {
     /// Output CSV file path.
     #[arg(short, long, required = false)]
     output_csv: Option<PathBuf>,
+
+    /// Run the sweep at each of these Reynolds numbers instead (e.g., "5e5,1e6,2e6"),
+    /// writing one long-format CSV with a `reynolds` column.
+    #[arg(long, value_parser = parse_reynolds, num_args = 1.., value_delimiter = ',')]
+    reynolds_list: Vec<u32>,
 }
 
 #[derive(Debug, ClapArgs)]
@@ -107,8 +129,13 @@ struct GetClArgs {
     aoa_step: f64,
 
     /// Output CSV file path for AoA and Cl data.
-    #[arg(short = 'o', long, default_value = "cl_data.csv")]
+    #[arg(short = 'o', long, default_value = DEFAULT_CL_OUTPUT)]
     output_csv: String,
+
+    /// Run the sweep at each of these Reynolds numbers instead (e.g., "5e5,1e6,2e6"),
+    /// writing one long-format CSV with a `reynolds` column.
+    #[arg(long, value_parser = parse_reynolds, num_args = 1.., value_delimiter = ',')]
+    reynolds_list: Vec<u32>,
 }
 
 #[derive(Debug, ClapArgs)]
@@ -133,7 +160,7 @@ struct SearchNacaArgs {
     aoa_step: f64,
 
     /// Output JSON file path for NACA code and best AoA data.
-    #[arg(short = 'o', long, default_value = "naca_search_results.csv")]
+    #[arg(short = 'o', long, default_value = DEFAULT_SEARCH_OUTPUT)]
     output_csv: String,
 
     /// Max camber percentages (M) for NACA 4-digit series (e.g., "0,2,4").
@@ -154,18 +181,245 @@ struct SearchNacaArgs {
     thickness_percent: Vec<u8>,
 }
 
+#[derive(Debug, ClapArgs)]
+struct InterpolateArgs {
+    /// NACA airfoil designation (e.g., "2412", "0012").
+    #[arg(short, long)]
+    naca: String,
+
+    /// Reynolds number.
+    #[arg(short, long, default_value_t = 1_000_000)]
+    reynolds: u32,
+
+    /// Minimum angle of attack for sweep (degrees).
+    #[arg(long, default_value_t = -5.0)]
+    min_aoa: f64,
+
+    /// Maximum angle of attack for sweep (degrees).
+    #[arg(long, default_value_t = 20.0)]
+    max_aoa: f64,
+
+    /// Angle of attack step for sweep (degrees).
+    #[arg(long, default_value_t = 0.5)]
+    aoa_step: f64,
+
+    /// Angles of attack to interpolate at (degrees, e.g., "3.25,7.8").
+    #[arg(short = 'q', long, num_args = 1.., value_delimiter = ',', required = true, allow_negative_numbers = true)]
+    query_aoa: Vec<f64>,
+
+    /// Output CSV file path for the interpolated data.
+    #[arg(short = 'o', long, default_value = DEFAULT_INTERPOLATE_OUTPUT)]
+    output_csv: String,
+}
+
 #[derive(Debug, ClapArgs)]
 struct LoadArgs {
+    /// Polar file, or a directory of polar files (e.g., a SearchNaca bulk directory).
     input: PathBuf,
-    #[arg(short = 'o', long, default_value = "foil_data.csv")]
+    #[arg(short = 'o', long, default_value = DEFAULT_LOAD_OUTPUT)]
     output: PathBuf,
 }
 
+/// Output path matching `format`: the `default` path takes the extension of the format,
+/// while a path with the extension of the other format is refused.
+fn resolve_output_path(
+    path: &Path,
+    default: Option<&str>,
+    format: OutputFormat,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if default.is_some_and(|default| path == Path::new(default)) {
+        return Ok(path.with_extension(format.extension()));
+    }
+    let other = match format {
+        OutputFormat::Csv => OutputFormat::Parquet,
+        OutputFormat::Parquet => OutputFormat::Csv,
+    };
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(other.extension()))
+    {
+        return Err(format!(
+            "Output file {} does not match --format {}, use a .{} extension",
+            path.display(),
+            format.extension(),
+            format.extension()
+        )
+        .into());
+    }
+    Ok(path.to_path_buf())
+}
+
+impl Commands {
+    /// Checks the output paths against `format`, switching the extension of defaulted ones.
+    fn resolve_output_paths(
+        &mut self,
+        format: OutputFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let resolve_string =
+            |path: &mut String, default: &str| -> Result<(), Box<dyn std::error::Error>> {
+                *path = resolve_output_path(Path::new(path.as_str()), Some(default), format)?
+                    .to_string_lossy()
+                    .into_owned();
+                Ok(())
+            };
+        match self {
+            Commands::Sweep(args) => {
+                if let Some(path) = &mut args.output_csv {
+                    *path = resolve_output_path(path, None, format)?;
+                }
+            }
+            Commands::GetCl(args) => resolve_string(&mut args.output_csv, DEFAULT_CL_OUTPUT)?,
+            Commands::SearchNaca(args) => {
+                resolve_string(&mut args.output_csv, DEFAULT_SEARCH_OUTPUT)?
+            }
+            Commands::Interpolate(args) => {
+                resolve_string(&mut args.output_csv, DEFAULT_INTERPOLATE_OUTPUT)?
+            }
+            Commands::Load(args) => {
+                args.output = resolve_output_path(&args.output, Some(DEFAULT_LOAD_OUTPUT), format)?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a Reynolds number, accepting scientific notation such as `5e5`.
+fn parse_reynolds(s: &str) -> Result<u32, String> {
+    let value: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid Reynolds number '{}'", s))?;
+    if !value.is_finite() || value < 1.0 || value > u32::MAX as f64 {
+        return Err(format!("Reynolds number '{}' is out of range", s));
+    }
+    Ok(value.round() as u32)
+}
+
+/// Polar file for one Reynolds number of a multi-Reynolds run, e.g. `polar_re500000.out`.
+fn reynolds_polar_path(polar_path: &Path, reynolds: u32) -> PathBuf {
+    let stem = polar_path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match polar_path.extension() {
+        Some(ext) => format!("{}_re{}.{}", stem, reynolds, ext.to_string_lossy()),
+        None => format!("{}_re{}", stem, reynolds),
+    };
+    polar_path.with_file_name(name)
+}
+
+/// Analysis results of one AoA sweep, keyed by its Reynolds number.
+type ReynoldsSweep = (u32, Vec<AnalysisResult>);
+
+/// Runs the same AoA sweep at every Reynolds number in parallel, keeping the input order.
+fn run_reynolds_sweeps(
+    xfoil_path: &PathBuf,
+    polar_path: &Path,
+    pool: &rayon::ThreadPool,
+    naca: &str,
+    (min_aoa, max_aoa, aoa_step): (f64, f64, f64),
+    reynolds_list: &[u32],
+) -> Result<Vec<ReynoldsSweep>, Box<dyn std::error::Error>> {
+    let sweeps: Result<Vec<_>, String> = pool.install(|| {
+        reynolds_list
+            .par_iter()
+            .progress_with(setup_progress_bar(
+                reynolds_list.len() as u64,
+                "Sweeping Re",
+            ))
+            .map(|&reynolds| {
+                FoxConfig::new(xfoil_path)
+                    .aoa_range(min_aoa, max_aoa, aoa_step)
+                    .polar_accumulation(reynolds_polar_path(polar_path, reynolds))
+                    .reynolds(reynolds as usize)
+                    .naca(naca)
+                    .get_runner()
+                    .and_then(|runner| runner.dispatch())
+                    .and_then(|runner| runner.get_output())
+                    .map(|output| (reynolds, output.export()))
+                    .map_err(|e| format!("Re = {}: {}", reynolds, e))
+            })
+            .collect()
+    });
+    Ok(sweeps?)
+}
+
+/// Writes multi-Reynolds sweep results as one row per (Reynolds, AoA) pair.
+fn write_reynolds_csv(
+    path: &Path,
+    sweeps: &[ReynoldsSweep],
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Writing results to {}...", path.display());
+    let mut wtr = TableWriter::create(path, format)?;
+    wtr.write_record(["reynolds", "aoa", "cl", "cd", "ld"])?;
+
+    for (reynolds, results) in sweeps {
+        for result in results {
+            wtr.write_record([
+                reynolds.to_string(),
+                result.aoa.to_string(),
+                result.cl.to_string(),
+                result.cd.to_string(),
+                result.ld_ratio.to_string(),
+            ])?;
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes every row of a polar with the polar file's column names.
+fn write_polar(
+    path: &Path,
+    polar: &XfoilResult,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = TableWriter::create(path, format)?;
+    wtr.write_record(XfoilResult::HEADERS)?;
+    for record in polar.records() {
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()
+}
+
 fn handle_sweep_command(
     xfoil_path: &PathBuf,
     polar_path: &PathBuf,
+    pool: &rayon::ThreadPool,
+    format: OutputFormat,
     args: &SweepArgs,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.reynolds_list.is_empty() {
+        println!(
+            "Analyzing NACA {} at Re = {:?} from AoA {:.1}° to {:.1}° (step {:.2}°)...",
+            args.naca, args.reynolds_list, args.min_aoa, args.max_aoa, args.aoa_step
+        );
+        let sweeps = run_reynolds_sweeps(
+            xfoil_path,
+            polar_path,
+            pool,
+            &args.naca,
+            (args.min_aoa, args.max_aoa, args.aoa_step),
+            &args.reynolds_list,
+        )?;
+        if let Some(path) = &args.output_csv {
+            write_reynolds_csv(path, &sweeps, format)?;
+        }
+
+        println!("\n--- Optimal Aerodynamic Performance per Reynolds Number ---");
+        for (reynolds, results) in &sweeps {
+            match results
+                .iter()
+                .max_by(|a, b| a.ld_ratio.total_cmp(&b.ld_ratio))
+            {
+                Some(best) => println!(
+                    "Re = {}: Best L/D {:.4} at AoA {:.2}° (Cl={:.4}, Cd={:.4})",
+                    reynolds, best.ld_ratio, best.aoa, best.cl, best.cd
+                ),
+                None => println!("Re = {}: No valid analysis result found!", reynolds),
+            }
+        }
+        return Ok(());
+    }
+
     println!(
         "Analyzing NACA {} at Re = {} from AoA {:.1}° to {:.1}° (step {:.2}°)...",
         args.naca, args.reynolds, args.min_aoa, args.max_aoa, args.aoa_step
@@ -180,12 +434,10 @@ fn handle_sweep_command(
         .expect("Failed to create runner")
         .dispatch()?
         .get_output()
-        .map(|out| {
+        .inspect(|out| {
             if let Some(path) = &args.output_csv {
                 println!("Writing results to {}", path.display());
-                out.to_csv(path).expect("Failed to write CSV")
-            } else {
-                out
+                write_polar(path, out, format).expect("Failed to write results");
             }
         })?
         .export()
@@ -199,8 +451,26 @@ fn handle_sweep_command(
 fn handle_get_cl_command(
     xfoil_path: &PathBuf,
     polar_path: &PathBuf,
+    pool: &rayon::ThreadPool,
+    format: OutputFormat,
     args: &GetClArgs,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.reynolds_list.is_empty() {
+        println!(
+            "Calculating Cl for NACA {} at Re = {:?} from AoA {:.2}° to {:.2}° (step {:.2}°)...",
+            args.naca, args.reynolds_list, args.min_aoa, args.max_aoa, args.aoa_step
+        );
+        let sweeps = run_reynolds_sweeps(
+            xfoil_path,
+            polar_path,
+            pool,
+            &args.naca,
+            (args.min_aoa, args.max_aoa, args.aoa_step),
+            &args.reynolds_list,
+        )?;
+        return write_reynolds_csv(Path::new(&args.output_csv), &sweeps, format);
+    }
+
     println!(
         "Calculating Cl for NACA {} at Re = {} from AoA {:.2}° to {:.2}° (step {:.2}°)...",
         args.naca, args.reynolds, args.min_aoa, args.max_aoa, args.aoa_step
@@ -220,9 +490,8 @@ fn handle_get_cl_command(
         .iter()
         .max_by(|a, b| a.ld_ratio.total_cmp(&b.ld_ratio)); // Note: this max_by result is not used.
     println!("Writing results to {}...", args.output_csv);
-    let mut wtr = csv::WriterBuilder::new()
-        .from_path(&args.output_csv)
-        .expect("Error creating CSV file");
+    let mut wtr =
+        TableWriter::create(&args.output_csv, format).expect("Error creating output file");
     wtr.write_record(&["aoa", "cl", "cd", "ld"])
         .expect("Error writing CSV header");
 
@@ -234,6 +503,7 @@ fn handle_get_cl_command(
             &result.ld_ratio.to_string(),
         ])?;
     }
+    wtr.flush()?;
 
     Ok(())
 }
@@ -366,9 +636,10 @@ fn analyze_single_naca(
 fn write_naca_search_results_to_csv(
     results: &[NacaBestAerodynamicPerformance],
     output_csv_path: &str,
+    format: OutputFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("Writing search results to {}...", output_csv_path);
-    let mut wtr = csv::WriterBuilder::new().from_path(output_csv_path)?;
+    let mut wtr = TableWriter::create(output_csv_path, format)?;
 
     wtr.write_record(&[
         "naca_code",
@@ -398,6 +669,8 @@ fn write_naca_search_results_to_csv(
 
 fn handle_search_naca_command(
     xfoil_path: &PathBuf,
+    pool: &rayon::ThreadPool,
+    format: OutputFormat,
     args: &SearchNacaArgs, // args.output_json will be used as the CSV file path
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!(
@@ -418,37 +691,147 @@ fn handle_search_naca_command(
     }
     println!("Generated {} unique NACA codes to process.", total_nacas);
 
-    let best_performances: Vec<NacaBestAerodynamicPerformance> = naca_codes_to_process
-        .par_iter()
-        .progress_with(setup_progress_bar(total_nacas as u64, "Searching best AoA"))
-        .filter_map(|naca_code| analyze_single_naca(naca_code, xfoil_path, args))
-        .collect();
+    let best_performances: Vec<NacaBestAerodynamicPerformance> = pool.install(|| {
+        naca_codes_to_process
+            .par_iter()
+            .progress_with(setup_progress_bar(total_nacas as u64, "Searching best AoA"))
+            .filter_map(|naca_code| analyze_single_naca(naca_code, xfoil_path, args))
+            .collect()
+    });
 
     // Use the output_json field from SearchNacaArgs as the path for the CSV file.
     // The name of the field in SearchNacaArgs is assumed to remain `output_json` for now,
     // but its help text and purpose have effectively changed.
-    write_naca_search_results_to_csv(&best_performances, &args.output_csv)?;
+    write_naca_search_results_to_csv(&best_performances, &args.output_csv, format)?;
 
     Ok(())
 }
 
-fn handle_load(xfoil_path: &PathBuf, args: &LoadArgs) -> Result<(), Box<dyn std::error::Error>> {
-    FoxConfig::new(xfoil_path)
-        .polar_accumulation(&args.input)
+fn handle_load(args: &LoadArgs, format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.input.is_dir() {
+        return write_polar(&args.output, &foxil::read_polar(&args.input)?, format);
+    }
+
+    let mut polar_files = std::fs::read_dir(&args.input)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path != &args.output)
+        .collect::<Vec<_>>();
+    polar_files.sort();
+
+    let mut wtr = TableWriter::create(&args.output, format)?;
+    let mut header = vec!["source"];
+    header.extend(XfoilResult::HEADERS);
+    wtr.write_record(&header)?;
+
+    let mut loaded = 0;
+    for path in &polar_files {
+        let source = path.file_name().unwrap_or_default().to_string_lossy();
+        let polar = match foxil::read_polar(path) {
+            Ok(polar) => polar,
+            Err(e) => {
+                eprintln!(
+                    "Failed to read polar file {}: {}. Skipping.",
+                    path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+        let mut rows = polar.records().peekable();
+        if rows.peek().is_none() {
+            eprintln!("No polar data in {}. Skipping.", path.display());
+            continue;
+        }
+        for record in rows {
+            wtr.write_record(
+                std::iter::once(source.as_ref()).chain(record.iter().map(String::as_str)),
+            )?;
+        }
+        loaded += 1;
+    }
+    wtr.flush()?;
+
+    println!(
+        "Loaded {} of {} polar files into {}.",
+        loaded,
+        polar_files.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn handle_interpolate_command(
+    xfoil_path: &PathBuf,
+    polar_path: &PathBuf,
+    format: OutputFormat,
+    args: &InterpolateArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!(
+        "Interpolating NACA {} at Re = {} from a sweep of AoA {:.2}° to {:.2}° (step {:.2}°)...",
+        args.naca, args.reynolds, args.min_aoa, args.max_aoa, args.aoa_step
+    );
+
+    let polar = FoxConfig::new(xfoil_path)
+        .aoa_range(args.min_aoa, args.max_aoa, args.aoa_step)
+        .polar_accumulation(polar_path)
+        .reynolds(args.reynolds as usize)
+        .naca(args.naca.as_str())
         .get_runner()?
-        .get_output()?
-        .to_csv(&args.output)?;
+        .dispatch()?
+        .get_output()?;
+
+    println!("Writing results to {}...", args.output_csv);
+    let mut wtr = TableWriter::create(&args.output_csv, format)?;
+    wtr.write_record(["aoa", "cl", "cd", "cm", "ld"])?;
+
+    for &aoa in &args.query_aoa {
+        let Some(point) = polar.interpolate(aoa) else {
+            eprintln!(
+                "AoA {:.2}° is outside the converged sweep range. Skipping.",
+                aoa
+            );
+            continue;
+        };
+        let ld_ratio = if point.cd.abs() < 1e-9 {
+            0.0
+        } else {
+            point.cl / point.cd
+        };
+        wtr.write_record(&[
+            point.aoa.to_string(),
+            point.cl.to_string(),
+            point.cd.to_string(),
+            point.cm.to_string(),
+            ld_ratio.to_string(),
+        ])?;
+    }
+    wtr.flush()?;
+
     Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    cli.command.resolve_output_paths(cli.format)?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.jobs)
+        .build()?;
 
     match &cli.command {
-        Commands::Sweep(args) => handle_sweep_command(&cli.xfoil_path, &cli.polar_path, args)?,
-        Commands::GetCl(args) => handle_get_cl_command(&cli.xfoil_path, &cli.polar_path, args)?,
-        Commands::SearchNaca(args) => handle_search_naca_command(&cli.xfoil_path, args)?,
-        Commands::Load(args) => handle_load(&cli.xfoil_path, args)?,
+        Commands::Sweep(args) => {
+            handle_sweep_command(&cli.xfoil_path, &cli.polar_path, &pool, cli.format, args)?
+        }
+        Commands::GetCl(args) => {
+            handle_get_cl_command(&cli.xfoil_path, &cli.polar_path, &pool, cli.format, args)?
+        }
+        Commands::SearchNaca(args) => {
+            handle_search_naca_command(&cli.xfoil_path, &pool, cli.format, args)?
+        }
+        Commands::Load(args) => handle_load(args, cli.format)?,
+        Commands::Interpolate(args) => {
+            handle_interpolate_command(&cli.xfoil_path, &cli.polar_path, cli.format, args)?
+        }
     }
 
     Ok(())
@@ -8,6 +8,8 @@ pub enum XfoilError {
     ParseError(num::ParseFloatError),
     ReadOutputError(std::string::FromUtf8Error),
     ConvergenceError,
+    NoAirfoil,
+    MissingOutput,
 }
 
 impl From<io::Error> for XfoilError {
@@ -30,7 +32,11 @@ impl From<std::string::FromUtf8Error> for XfoilError {
 
 impl fmt::Display for XfoilError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Internal xfoil error")
+        match self {
+            XfoilError::NoAirfoil => write!(f, "Xfoil cannot run without airfoil"),
+            XfoilError::MissingOutput => write!(f, "Xfoil was not run, no output captured"),
+            _ => write!(f, "Internal xfoil error"),
+        }
     }
 }
 
@@ -17,6 +17,13 @@ pub enum Mode {
     Cl(f64),
 }
 
+/// Trailing-edge flap applied to the airfoil before analysis.
+struct Flap {
+    x_hinge: f64,
+    y_hinge: f64,
+    deflection: f64,
+}
+
 /// Struct tracking Xfoil configuration.
 pub struct FoxConfig {
     mode: Mode,
@@ -25,6 +32,9 @@ pub struct FoxConfig {
     polar: Option<PathBuf>,
     naca: Option<String>,
     dat_file: Option<PathBuf>,
+    flap: Option<Flap>,
+    iter_limit: Option<usize>,
+    dump_commands: bool,
 }
 
 impl FoxConfig {
@@ -37,11 +47,14 @@ impl FoxConfig {
             polar: None,
             naca: None,
             dat_file: None,
+            flap: None,
+            iter_limit: None,
+            dump_commands: false,
         }
     }
 
     /// Construct XfoilRunner from configuration
-    /// panics: if no airfoil (either from polar file or NACA code) is given.
+    /// errors: `XfoilError::NoAirfoil` if no airfoil (either from polar file or NACA code) is given.
     pub fn get_runner(mut self) -> error::Result<XfoilRunner> {
         let mut command_sequence = vec!["plop", "G", ""]
             .into_iter()
@@ -56,15 +69,37 @@ impl FoxConfig {
                 "".to_string(),
             ]);
         } else {
-            panic!("Xfoil cannot run without airfoil");
+            return Err(error::XfoilError::NoAirfoil);
+        }
+
+        // Deflect the flap in GDES, then register the same hinge in OPER so the
+        // polar gets a hinge-moment column
+        if let Some(flap) = &self.flap {
+            command_sequence.extend_from_slice(&[
+                "gdes".to_string(),
+                "flap".to_string(),
+                flap.x_hinge.to_string(),
+                flap.y_hinge.to_string(),
+                flap.deflection.to_string(),
+                "exec".to_string(),
+                "".to_string(),
+            ]);
         }
 
         command_sequence.push("oper".to_string());
 
+        if let Some(flap) = &self.flap {
+            command_sequence.push(format!("fnew {} {}", flap.x_hinge, flap.y_hinge));
+        }
+
         if let Some(reynolds) = self.reynolds {
             command_sequence.push(format!("v {reynolds}").to_string());
         }
 
+        if let Some(iter_limit) = self.iter_limit {
+            command_sequence.push(format!("iter {iter_limit}"));
+        }
+
         self.polar = if let Some(polar) = self.polar {
             command_sequence.extend_from_slice(&[
                 "pacc".to_string(),
@@ -73,6 +108,12 @@ impl FoxConfig {
             ]);
             Some(polar)
         } else {
+            // Accumulate in memory only, the table is listed to stdout by `plis`
+            command_sequence.extend_from_slice(&[
+                "pacc".to_string(),
+                "".to_string(),
+                "".to_string(),
+            ]);
             None
         };
 
@@ -88,12 +129,18 @@ impl FoxConfig {
                 .extend_from_slice(&[format!("aseq {start} {end} {step}").to_string()]),
         }
 
+        if self.polar.is_none() {
+            command_sequence.push("plis".to_string());
+        }
+
         command_sequence.push("".to_string());
         command_sequence.push("quit".to_string());
         Ok(XfoilRunner {
             xfoil_path: self.path,
             command_sequence,
             polar: self.polar,
+            stdout: None,
+            dump_commands: self.dump_commands,
         })
     }
 
@@ -144,6 +191,30 @@ impl FoxConfig {
         self
     }
 
+    /// Deflect a trailing-edge flap hinged at (`x_hinge`, `y_hinge`) by `deflection` degrees
+    /// (positive down) and record the flap hinge moment in the results.
+    pub fn flap(mut self, x_hinge: f64, y_hinge: f64, deflection: f64) -> Self {
+        self.flap = Some(Flap {
+            x_hinge,
+            y_hinge,
+            deflection,
+        });
+        self
+    }
+
+    /// Set the maximum number of viscous iterations per operating point.
+    pub fn iter_limit(mut self, iterations: usize) -> Self {
+        self.iter_limit = Some(iterations);
+        self
+    }
+
+    /// Print the command sequence fed to Xfoil on stderr before it is run, so a failing
+    /// run can be replayed in an interactive session.
+    pub fn dump_commands(mut self, dump: bool) -> Self {
+        self.dump_commands = dump;
+        self
+    }
+
     /// Set a Reynolds number for a viscous calculation.
     pub fn reynolds(mut self, reynolds: usize) -> Self {
         self.reynolds = Some(reynolds);
@@ -155,15 +226,23 @@ pub struct XfoilRunner {
     xfoil_path: PathBuf,
     command_sequence: Vec<String>,
     polar: Option<PathBuf>,
+    stdout: Option<String>,
+    dump_commands: bool,
 }
 
 impl XfoilRunner {
+    /// The commands fed to Xfoil's stdin, one per line.
+    pub fn command_sequence(&self) -> &[String] {
+        &self.command_sequence
+    }
+
     /// Run Xfoil calculation. This method dispatches a child process, and feeds
     /// a sequence of commands to its stdin. After the calculation finishes,
     /// it outputs the contents of the resulting polar file in a HashMap.
     /// This method panics if something goes wrong either executing the child
     /// process, or retrieving a handle to its stdin. It may return an XfoilError
     /// if anything goes wrong writing to the process or parsing its output.
+    /// The captured stdout is kept for `get_output_from_stdout`.
     pub fn dispatch(self) -> error::Result<Self> {
         if let Some(polar_path) = &self.polar {
             if polar_path.exists() {
@@ -176,6 +255,10 @@ impl XfoilRunner {
             }
         }
 
+        if self.dump_commands {
+            eprintln!("{}", self.command_sequence.join("\n"));
+        }
+
         let mut child = Command::new(&self.xfoil_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
@@ -200,39 +283,155 @@ impl XfoilRunner {
         }
 
         // If the calculation did not convergence, return ConvergenceError
-        let _ = child
+        let output = child
             .wait_with_output()
             .expect("Failed to retrieve child output");
 
-        Ok(self)
+        Ok(Self {
+            stdout: Some(String::from_utf8(output.stdout)?),
+            ..self
+        })
     }
 
     pub fn get_output(self) -> error::Result<XfoilResult> {
-        let mut result = HashMap::new();
-        let table_header = ["alpha", "CL", "CD", "CDp", "CM", "Top_Xtr", "Bot_Xtr"];
-        for header in &table_header {
-            result.insert(header.to_string(), Vec::<f64>::new());
+        read_polar(self.polar.expect("polar file not found"))
+    }
+
+    /// Parse the polar table XFoil printed to stdout during `dispatch`, so runs without
+    /// `polar_accumulation` need no file round-trip.
+    pub fn get_output_from_stdout(self) -> error::Result<XfoilResult> {
+        let stdout = self.stdout.ok_or(error::XfoilError::MissingOutput)?;
+        Ok(parse_polar_table(stdout.lines().map(str::to_string)))
+    }
+}
+
+/// Read a polar file previously written by XFoil, without running it.
+pub fn read_polar<T: AsRef<Path>>(path: T) -> error::Result<XfoilResult> {
+    let lines = BufReader::new(File::open(path)?)
+        .lines()
+        .collect::<std::io::Result<Vec<_>>>()?;
+    Ok(parse_polar_table(lines))
+}
+
+/// Parse an XFoil polar table, either from a polar file or from the `plis` listing on stdout.
+///
+/// The table starts after the `alpha CL ...` column names and its dashed separator, and ends
+/// at the first line that is not all numbers.
+fn parse_polar_table<I: IntoIterator<Item = String>>(lines: I) -> XfoilResult {
+    let mut result = HashMap::new();
+    let mut table_header = vec!["alpha", "CL", "CD", "CDp", "CM", "Top_Xtr", "Bot_Xtr"];
+    for header in &table_header {
+        result.insert(header.to_string(), Vec::<f64>::new());
+    }
+
+    let mut lines = lines
+        .into_iter()
+        .skip_while(|line| line.split_whitespace().next() != Some("alpha"));
+
+    // A flap hinge adds a `Chinge` column after the transition locations
+    if let Some(names) = lines.next()
+        && let Some(idx) = names.split_whitespace().position(|name| name == "Chinge")
+    {
+        table_header.resize(idx, "");
+        table_header.push("Cm_hinge");
+        result.insert("Cm_hinge".to_string(), Vec::<f64>::new());
+    }
+    for line in lines.skip(1) {
+        let Ok(data) = line
+            .split_whitespace()
+            .map(|x| x.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+        else {
+            break;
+        };
+        if data.is_empty() {
+            break;
         }
-        // number of lines in Xfoil polar header
-        const HEADER: usize = 13;
-        for line in BufReader::new(File::open(self.polar.expect("polar file not found"))?)
-            .lines()
-            .skip(HEADER - 1)
-        {
-            let data = line?
-                .split_whitespace()
-                .map(|x| x.parse::<f64>().expect("Failed to parse Xfoil polar"))
-                .collect::<Vec<_>>();
-            for (&header, value) in table_header.iter().zip(data) {
-                result
-                    .get_mut(header)
-                    .expect("Failed to retrieve result HashMap")
-                    .push(value);
+        for (&header, value) in table_header.iter().zip(data) {
+            if let Some(column) = result.get_mut(header) {
+                column.push(value);
             }
         }
-        Ok(
-            serde_json::from_value(serde_json::json!(result))
-                .expect("Failed to deserialize result"),
+    }
+    serde_json::from_value(serde_json::json!(result)).expect("Failed to deserialize result")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLAR_HEADER: &str = "
+       XFOIL         Version 6.99
+
+ Calculated polar for: NACA 0012
+
+ 1 1 Reynolds number fixed          Mach number fixed
+
+ xtrf =   1.000 (top)        1.000 (bottom)
+ Mach =   0.000     Re =     1.000 e 6     Ncrit =   9.000
+
+   alpha    CL        CD       CDp       CM     Top_Xtr  Bot_Xtr  Chinge
+  ------ -------- --------- --------- -------- -------- -------- --------
+";
+
+    #[test]
+    fn test_get_output_reads_hinge_moment_column() {
+        let polar = std::env::temp_dir().join("foxil_hinge_polar.txt");
+        std::fs::write(
+            &polar,
+            format!(
+                "{POLAR_HEADER}   0.000   0.3891   0.00612   0.00121  -0.0710   0.6150   1.0000  -0.0321\n"
+            ),
         )
+        .unwrap();
+
+        let runner = XfoilRunner {
+            xfoil_path: PathBuf::new(),
+            command_sequence: Vec::new(),
+            polar: Some(polar.clone()),
+            stdout: None,
+            dump_commands: false,
+        };
+        let result = runner.get_output().unwrap();
+        std::fs::remove_file(polar).unwrap();
+
+        assert_eq!(result.cm_hinge(), &[-0.0321]);
+        assert_eq!(result.get_analysis_result(0.0).cl, 0.3891);
+    }
+
+    #[test]
+    fn test_get_output_from_stdout_stops_at_end_of_table() {
+        let stdout = format!(
+            "{POLAR_HEADER}   2.000   0.2416   0.00567   0.00097  -0.0010   0.5123   0.9820\n\n OPERv   c>  "
+        );
+        let runner = XfoilRunner {
+            xfoil_path: PathBuf::new(),
+            command_sequence: Vec::new(),
+            polar: None,
+            stdout: Some(stdout),
+            dump_commands: false,
+        };
+        let result = runner.get_output_from_stdout().unwrap();
+
+        assert_eq!(result.export().len(), 1);
+        assert_eq!(result.get_analysis_result(2.0).cd, 0.00567);
+    }
+
+    #[test]
+    fn test_command_sequence_places_flap_before_oper() {
+        let runner = FoxConfig::new("xfoil")
+            .naca("0012")
+            .flap(0.75, 0.0, 5.0)
+            .iter_limit(200)
+            .aoa(2.0)
+            .get_runner()
+            .unwrap();
+        let commands = runner.command_sequence();
+        let position = |command: &str| commands.iter().position(|c| c == command).unwrap();
+
+        assert!(position("gdes") < position("oper"));
+        assert!(position("oper") < position("iter 200"));
+        assert!(position("fnew 0.75 0") < position("a 2"));
+        assert_eq!(commands.last().map(String::as_str), Some("quit"));
     }
 }
@@ -17,6 +17,8 @@ pub struct XfoilResult {
     top_xtr: Vec<f64>,
     #[serde(rename = "Bot_Xtr")]
     bot_xtr: Vec<f64>,
+    #[serde(rename = "Cm_hinge", default)]
+    cm_hinge: Vec<f64>,
 }
 
 impl XfoilResult {
@@ -29,6 +31,41 @@ impl XfoilResult {
             AnalysisResult::default()
         }
     }
+    /// Flap hinge moment coefficients, one per alpha; empty unless a flap was configured.
+    pub fn cm_hinge(&self) -> &[f64] {
+        &self.cm_hinge
+    }
+
+    /// Linearly interpolate CL, CD and CM at `aoa` from the two polar rows bracketing it.
+    /// Returns `None` if `aoa` lies outside the computed alpha range.
+    pub fn interpolate(&self, aoa: f64) -> Option<InterpolatedResult> {
+        let mut order = (0..self.alpha.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| self.alpha[a].total_cmp(&self.alpha[b]));
+
+        let upper = order.iter().position(|&idx| self.alpha[idx] >= aoa)?;
+        let hi = order[upper];
+        let lo = if self.alpha[hi] == aoa {
+            hi
+        } else {
+            order[upper.checked_sub(1)?]
+        };
+
+        let span = self.alpha[hi] - self.alpha[lo];
+        let t = if span == 0.0 {
+            0.0
+        } else {
+            (aoa - self.alpha[lo]) / span
+        };
+        let lerp = |values: &[f64]| values[lo] + (values[hi] - values[lo]) * t;
+
+        Some(InterpolatedResult {
+            aoa,
+            cl: lerp(&self.cl),
+            cd: lerp(&self.cd),
+            cm: lerp(&self.cm),
+        })
+    }
+
     pub fn export(&self) -> Vec<AnalysisResult> {
         self.alpha
             .iter()
@@ -36,32 +73,35 @@ impl XfoilResult {
             .collect()
     }
 
+    /// Column names of the polar table, in the order `records` yields them.
+    pub const HEADERS: [&'static str; 7] = ["alpha", "CL", "CD", "CDp", "CM", "Top_Xtr", "Bot_Xtr"];
+
+    /// One stringified row per alpha value, matching `HEADERS`.
+    pub fn records(&self) -> impl Iterator<Item = [String; 7]> + '_ {
+        // Assumes all Vec<f64> fields in XfoilResult have the same length,
+        // corresponding to the number of alpha values.
+        // This should be guaranteed by the parsing logic that creates XfoilResult.
+        (0..self.alpha.len()).map(|i| {
+            [
+                self.alpha[i].to_string(),
+                self.cl[i].to_string(),
+                self.cd[i].to_string(),
+                self.cd_p[i].to_string(),
+                self.cm[i].to_string(),
+                self.top_xtr[i].to_string(),
+                self.bot_xtr[i].to_string(),
+            ]
+        })
+    }
+
     pub fn to_csv(self, path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         let mut wtr = csv::Writer::from_path(path)?;
 
         // Write headers matching the XfoilResult fields
-        wtr.write_record(["alpha", "CL", "CD", "CDp", "CM", "Top_Xtr", "Bot_Xtr"])?;
+        wtr.write_record(Self::HEADERS)?;
 
-        // Iterate over the data points.
-        // Assumes all Vec<f64> fields in XfoilResult have the same length,
-        // corresponding to the number of alpha values.
-        // This should be guaranteed by the parsing logic that creates XfoilResult.
-        if !self.alpha.is_empty() {
-            for i in 0..self.alpha.len() {
-                // It's generally safe to use direct indexing if the invariant holds.
-                // Otherwise, .get(i).copied().unwrap_or_default() could be used for robustness,
-                // but that would mask data integrity issues.
-                let record = [
-                    self.alpha[i].to_string(),
-                    self.cl[i].to_string(),
-                    self.cd[i].to_string(),
-                    self.cd_p[i].to_string(),
-                    self.cm[i].to_string(),
-                    self.top_xtr[i].to_string(),
-                    self.bot_xtr[i].to_string(),
-                ];
-                wtr.write_record(&record)?;
-            }
+        for record in self.records() {
+            wtr.write_record(&record)?;
         }
 
         wtr.flush()?; // Ensure all data is written to the underlying writer.
@@ -69,6 +109,14 @@ impl XfoilResult {
     }
 }
 
+/// Aerodynamic coefficients interpolated between polar rows.
+pub struct InterpolatedResult {
+    pub aoa: f64,
+    pub cl: f64,
+    pub cd: f64,
+    pub cm: f64,
+}
+
 #[derive(Default)]
 pub struct AnalysisResult {
     pub aoa: f64,
@@ -88,3 +136,27 @@ impl AnalysisResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_between_bracketing_rows() {
+        let result = XfoilResult {
+            alpha: vec![3.5, 3.0],
+            cl: vec![0.6, 0.4],
+            cd: vec![0.012, 0.010],
+            cm: vec![-0.02, -0.04],
+            ..Default::default()
+        };
+
+        let point = result.interpolate(3.25).unwrap();
+        assert!((point.cl - 0.5).abs() < 1e-12);
+        assert!((point.cd - 0.011).abs() < 1e-12);
+        assert!((point.cm + 0.03).abs() < 1e-12);
+        assert_eq!(result.interpolate(3.0).unwrap().cl, 0.4);
+        assert!(result.interpolate(2.5).is_none());
+        assert!(result.interpolate(4.0).is_none());
+    }
+}
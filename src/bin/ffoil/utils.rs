@@ -1,6 +1,101 @@
 use crate::SweepArgs;
+use clap::ValueEnum;
 use foxil::result::AnalysisResult;
 use indicatif::{ProgressBar, ProgressStyle};
+use polars::prelude::*;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// File format of the tables written by the subcommands.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Csv,
+    Parquet,
+}
+
+impl OutputFormat {
+    /// File extension of the tables written in this format, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Writes a table record by record, the first record being the header.
+///
+/// CSV is streamed to disk; Parquet is columnar, so records are buffered and the file is
+/// written by `flush`, with columns stored as floats when every value parses as one.
+pub enum TableWriter {
+    Csv(Box<csv::Writer<File>>),
+    Parquet {
+        path: PathBuf,
+        records: Vec<Vec<String>>,
+    },
+}
+
+impl TableWriter {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        format: OutputFormat,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match format {
+            OutputFormat::Csv => TableWriter::Csv(Box::new(csv::Writer::from_path(path)?)),
+            OutputFormat::Parquet => TableWriter::Parquet {
+                path: path.as_ref().to_path_buf(),
+                records: Vec::new(),
+            },
+        })
+    }
+
+    pub fn write_record<I, T>(&mut self, record: I) -> Result<(), Box<dyn std::error::Error>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        match self {
+            TableWriter::Csv(wtr) => {
+                wtr.write_record(record.into_iter().map(|f| f.as_ref().to_string()))?
+            }
+            TableWriter::Parquet { records, .. } => {
+                records.push(record.into_iter().map(|f| f.as_ref().to_string()).collect())
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            TableWriter::Csv(wtr) => wtr.flush()?,
+            TableWriter::Parquet { path, records } => {
+                let Some((header, rows)) = records.split_first() else {
+                    return Ok(());
+                };
+                let columns = header
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, name)| {
+                        let values = rows
+                            .iter()
+                            .map(|row| row.get(idx).map_or("", String::as_str));
+                        match values
+                            .clone()
+                            .map(str::parse::<f64>)
+                            .collect::<Result<Vec<_>, _>>()
+                        {
+                            Ok(numbers) => Column::new(name.into(), numbers),
+                            Err(_) => Column::new(name.into(), values.collect::<Vec<_>>()),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let mut df = DataFrame::new(columns)?;
+                ParquetWriter::new(File::create(path)?).finish(&mut df)?;
+            }
+        }
+        Ok(())
+    }
+}
 
 pub fn display_analysis_summary(args: &SweepArgs, result: &AnalysisResult) {
     println!("\n--- Optimal Aerodynamic Performance (Sweep) ---");
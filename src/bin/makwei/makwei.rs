@@ -1,14 +1,17 @@
+#[cfg(test)]
+mod tests;
 mod utils;
 
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use indicatif::{ParallelProgressIterator, ProgressBar};
 use kmeans::*;
-use ndarray::{Array1, Array2};
+use ndarray::{Array1, Array2, Axis};
 use polars::prelude::*;
 use rand::prelude::*;
 use rayon::prelude::*;
 use scirs2_metrics::clustering;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(author, about)]
@@ -19,14 +22,23 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     Nop(Nop),
+    /// Fit KMeans with a fixed number of clusters and save the centroids
+    Fit(Fit),
+    /// Label a table by the nearest centroid of a saved model
+    Assign(Assign),
 }
 
 #[derive(Debug, Args)]
 struct Nop {
     input: PathBuf,
 
-    #[arg(short, long, default_value = "wind")]
-    wind_field: String,
+    /// Input file format, detected from the extension when omitted
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// Comma-separated column names used as clustering features
+    #[arg(short, long, default_value = "wind", value_delimiter = ',')]
+    wind_field: Vec<String>,
 
     #[arg(short = 'c', long, default_value_t = 4000)]
     sample_count: u32,
@@ -42,12 +54,106 @@ struct Nop {
     weight_ch: f64,
     #[arg(short = 'D', long, default_value_t = 0.33)]
     weight_db: f64,
+    #[arg(short = 'I', long, default_value_t = 0.25)]
+    weight_i: f64,
 
     #[arg(short = 'N', long, default_value = "probability")]
     norm_method: String,
 
+    /// Seed for KMeans initialization and silhouette sampling, random when omitted
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Do not print the intermediate weight vectors
+    #[arg(short, long, default_value_t = false)]
+    quiet: bool,
+
     #[arg(short, long, default_value = "output_scores.csv")]
     output: PathBuf,
+
+    /// File format of the score dump
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+}
+
+/// File format of written tables.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Csv,
+    Parquet,
+}
+
+/// File format of the input table.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum InputFormat {
+    Csv,
+    Parquet,
+    Ndjson,
+}
+
+impl InputFormat {
+    /// Picks the format from the file extension, falling back to CSV.
+    fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("parquet") => InputFormat::Parquet,
+            Some(ext)
+                if ext.eq_ignore_ascii_case("ndjson") || ext.eq_ignore_ascii_case("jsonl") =>
+            {
+                InputFormat::Ndjson
+            }
+            _ => InputFormat::Csv,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct Fit {
+    input: PathBuf,
+
+    /// Input file format, detected from the extension when omitted
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// Comma-separated column names used as clustering features
+    #[arg(short, long, default_value = "wind", value_delimiter = ',')]
+    wind_field: Vec<String>,
+
+    #[arg(short = 'k', long)]
+    n_clusters: u32,
+
+    /// Seed for KMeans initialization, random when omitted
+    #[arg(long)]
+    seed: Option<u64>,
+
+    #[arg(short, long, default_value = "kmeans_model.json")]
+    output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct Assign {
+    input: PathBuf,
+
+    /// Input file format, detected from the extension when omitted
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
+
+    #[arg(short, long, default_value = "kmeans_model.json")]
+    model: PathBuf,
+
+    /// Feature columns to read, defaults to the ones the model was fitted on
+    #[arg(short, long, value_delimiter = ',')]
+    wind_field: Option<Vec<String>>,
+
+    #[arg(short, long, default_value = "assignments.csv")]
+    output: PathBuf,
+}
+
+/// Centroids of a fitted KMeans model, stored row by row.
+#[derive(Debug, Serialize, Deserialize)]
+struct KMeansModel {
+    fields: Vec<String>,
+    n_features: usize,
+    centroids: Vec<f64>,
 }
 
 #[derive(Clone, Copy)]
@@ -56,153 +162,290 @@ enum IndicatorType {
     Negative,
 }
 
-fn opt_best_n_state(arg: Nop) -> Result<(), Box<dyn std::error::Error>> {
-    let data: Vec<f64> = LazyCsvReader::new(arg.input)
-        .with_has_header(true)
-        .finish()?
-        .select([col(&arg.wind_field)])
-        .collect()?
-        .column(&arg.wind_field)?
-        .f64()?
-        .to_vec()
+/// Opens the input table lazily, detecting the format from the extension when not given.
+fn scan_input(input: &Path, format: Option<InputFormat>) -> PolarsResult<LazyFrame> {
+    match format.unwrap_or_else(|| InputFormat::detect(input)) {
+        InputFormat::Csv => LazyCsvReader::new(input).with_has_header(true).finish(),
+        InputFormat::Parquet => LazyFrame::scan_parquet(input, ScanArgsParquet::default()),
+        InputFormat::Ndjson => LazyJsonLineReader::new(input).finish(),
+    }
+}
+
+/// Reads the given columns into a `(n_samples, n_features)` matrix.
+fn read_feature_matrix(
+    input: &Path,
+    format: Option<InputFormat>,
+    fields: &[String],
+) -> Result<Array2<f64>, Box<dyn std::error::Error>> {
+    let df = scan_input(input, format)?
+        .select(fields.iter().map(col).collect::<Vec<_>>())
+        .collect()?;
+
+    let (features, _) = feature_matrix(&df, fields)?;
+    Ok(features)
+}
+
+/// Extracts the given columns of `df` into a `(n_samples, n_features)` matrix.
+///
+/// Rows with a missing or NaN value in any column are dropped; the returned mask
+/// marks which rows of `df` were kept.
+fn feature_matrix(
+    df: &DataFrame,
+    fields: &[String],
+) -> Result<(Array2<f64>, Vec<bool>), Box<dyn std::error::Error>> {
+    let columns = fields
         .iter()
-        .map(|x| x.expect("Bad value!"))
+        .map(|field| Ok(df.column(field)?.cast(&DataType::Float64)?.f64()?.to_vec()))
+        .collect::<Result<Vec<Vec<Option<f64>>>, PolarsError>>()?;
+
+    let valid: Vec<bool> = (0..df.height())
+        .map(|i| {
+            columns
+                .iter()
+                .all(|column| column[i].is_some_and(|x| !x.is_nan()))
+        })
         .collect();
-    println!("Read {} data points.", data.len());
+    let kept: Vec<usize> = (0..df.height()).filter(|&i| valid[i]).collect();
 
-    let kmean: KMeans<f64, 16, _> = KMeans::new(&data, data.len(), 1, EuclideanDistance);
+    if kept.is_empty() {
+        return Err(format!("No valid values found in {:?}", fields).into());
+    }
+    let dropped = df.height() - kept.len();
+    if dropped > 0 {
+        println!("Dropped {} row(s) with missing or NaN values.", dropped);
+    }
 
-    let task: Vec<u32> = (arg.start..arg.end).collect();
+    let features = Array2::from_shape_fn((kept.len(), columns.len()), |(i, j)| {
+        columns[j][kept[i]].unwrap()
+    });
+    Ok((features, valid))
+}
+
+fn build_kmeans(features: &Array2<f64>) -> KMeans<f64, 16, EuclideanDistance> {
+    let (n_samples, n_features) = features.dim();
+    let data: Vec<f64> = features.iter().copied().collect();
+    KMeans::new(&data, n_samples, n_features, EuclideanDistance)
+}
+
+fn fit_kmeans(kmean: &KMeans<f64, 16, EuclideanDistance>, n: u32, seed: u64) -> KMeansState<f64> {
+    let config = KMeansConfig::build()
+        .random_generator(StdRng::seed_from_u64(seed))
+        .build();
+    kmean.kmeans_lloyd(
+        n as usize,
+        u64::MAX as usize,
+        KMeans::init_kmeanplusplus,
+        &config,
+    )
+}
+
+fn fit_model(arg: Fit) -> Result<(), Box<dyn std::error::Error>> {
+    if arg.n_clusters == 0 {
+        return Err("n-clusters must be at least 1".into());
+    }
+
+    let features = read_feature_matrix(&arg.input, arg.input_format, &arg.wind_field)?;
+    let (n_samples, n_features) = features.dim();
+    if n_samples < arg.n_clusters as usize {
+        return Err(format!(
+            "Cannot fit {} clusters on {} data points",
+            arg.n_clusters, n_samples
+        )
+        .into());
+    }
+    println!(
+        "Read {} data points with {} feature(s).",
+        n_samples, n_features
+    );
+
+    let seed = arg.seed.unwrap_or_else(|| thread_rng().gen());
+    println!("Using seed {}", seed);
+
+    let res = fit_kmeans(&build_kmeans(&features), arg.n_clusters, seed);
+    let model = KMeansModel {
+        fields: arg.wind_field,
+        n_features,
+        centroids: res.centroids.to_vec(),
+    };
+
+    std::fs::write(&arg.output, serde_json::to_string_pretty(&model)?)?;
+    println!("Model dumped to {}", arg.output.display());
+    Ok(())
+}
+
+fn assign_clusters(arg: Assign) -> Result<(), Box<dyn std::error::Error>> {
+    let model: KMeansModel = serde_json::from_str(&std::fs::read_to_string(&arg.model)?)?;
+    let fields = arg.wind_field.unwrap_or_else(|| model.fields.clone());
+    if fields.len() != model.n_features {
+        return Err(format!(
+            "Model expects {} feature(s), got {}",
+            model.n_features,
+            fields.len()
+        )
+        .into());
+    }
+
+    let mut df = scan_input(&arg.input, arg.input_format)?.collect()?;
+    let (features, valid) = feature_matrix(&df, &fields)?;
+    if valid.contains(&false) {
+        df = df.filter(&BooleanChunked::from_slice("valid".into(), &valid))?;
+    }
+
+    let assignments: Vec<u32> = features
+        .outer_iter()
+        .map(|sample| utils::nearest_centroid(sample.as_slice().unwrap(), &model.centroids) as u32)
+        .collect();
+    df.with_column(Column::new("assignment".into(), assignments))?;
+
+    let file = std::fs::File::create(&arg.output)?;
+    CsvWriter::new(file).include_header(true).finish(&mut df)?;
+
+    println!("Assignments dumped to {}", arg.output.display());
+    Ok(())
+}
+
+fn opt_best_n_state(arg: Nop) -> Result<(), Box<dyn std::error::Error>> {
+    if arg.start < 2 {
+        return Err(format!(
+            "start must be at least 2 (silhouette needs two clusters), got {}",
+            arg.start
+        )
+        .into());
+    }
+    if arg.start > arg.end {
+        return Err(format!(
+            "start ({}) must not be greater than end ({})",
+            arg.start, arg.end
+        )
+        .into());
+    }
+
+    let features = read_feature_matrix(&arg.input, arg.input_format, &arg.wind_field)?;
+    let (n_samples, n_features) = features.dim();
+    println!(
+        "Read {} data points with {} feature(s).",
+        n_samples, n_features
+    );
+
+    let kmean = build_kmeans(&features);
+    let seed = arg.seed.unwrap_or_else(|| thread_rng().gen());
+    println!("Using seed {}", seed);
+
+    let task: Vec<u32> = (arg.start..=arg.end).collect();
 
     let scores = task
         .par_iter()
         .progress_with(ProgressBar::new(task.len() as u64))
         .map(|&n| {
-            let res = kmean.kmeans_lloyd(
-                n as usize,
-                u64::MAX as usize,
-                KMeans::init_kmeanplusplus,
-                &KMeansConfig::default(),
-            );
+            // Derive a per-k seed so results do not depend on the parallel scheduling order
+            let task_seed = seed.wrapping_add(n as u64);
+            let res = fit_kmeans(&kmean, n, task_seed);
 
             let sample_limit = arg.sample_count as usize;
-            let (data_for_silhouette, assignments_for_silhouette, n_samples_silhouette): (
-                Vec<f64>,
-                Vec<usize>,
-                usize,
-            );
+            let (features_for_silhouette, assignments_for_silhouette): (Array2<f64>, Vec<usize>);
 
-            if data.len() > sample_limit {
+            if n_samples > sample_limit {
                 // Data size exceeds limit, perform random sampling
-                // Assumes `use rand::thread_rng;` and `use rand::seq::SliceRandom;` are present or IDE will add them.
-                let mut rng = thread_rng();
-                let all_indices: Vec<usize> = (0..data.len()).collect();
+                let mut rng = StdRng::seed_from_u64(task_seed);
+                let all_indices: Vec<usize> = (0..n_samples).collect();
 
                 let chosen_indices: Vec<usize> = all_indices
                     .choose_multiple(&mut rng, sample_limit)
                     .cloned()
                     .collect();
 
-                data_for_silhouette = chosen_indices.iter().map(|&i| data[i]).collect();
+                features_for_silhouette = features.select(Axis(0), &chosen_indices);
                 assignments_for_silhouette =
                     chosen_indices.iter().map(|&i| res.assignments[i]).collect();
-                n_samples_silhouette = sample_limit;
-                // println!("Data length ({}) > sample_count ({}). Randomly sampled {} points for Silhouette score.", data.len(), sample_limit, n_samples_silhouette);
             } else {
                 // Data size is within limit (or equal), use all data
-                data_for_silhouette = data.clone();
+                features_for_silhouette = features.clone();
                 assignments_for_silhouette = res.assignments.clone();
-                n_samples_silhouette = data.len();
-                // println!("Data length ({}) <= sample_count ({}). Using all {} points for Silhouette score.", data.len(), sample_limit, n_samples_silhouette);
             }
 
             let silhouette = clustering::silhouette_score(
-                &Array2::from_shape_vec(
-                    (n_samples_silhouette, 2), // Use the actual number of samples for silhouette
-                    data_for_silhouette
-                        .iter()
-                        .flat_map(|&x| vec![x, 0.0])
-                        .collect(),
-                )
-                .expect("Bad shape"),
+                &features_for_silhouette,
                 &Array1::from_vec(assignments_for_silhouette), // Use the (potentially sampled) assignments
                 "euclidean",
             )
             .expect("Bad shape");
 
             let calinski_harabasz = clustering::calinski_harabasz_score(
-                &Array2::from_shape_vec(
-                    (data.len(), 2),
-                    data.iter().flat_map(|&x| vec![x, 0.0]).collect(),
-                )
-                .expect("Bad shape"),
+                &features,
                 &Array1::from_vec(res.assignments.clone()),
             )
             .expect("Bad shape");
-            let davies_bouldin = clustering::davies_bouldin_score(
-                &Array2::from_shape_vec(
-                    (data.len(), 2),
-                    data.iter().flat_map(|&x| vec![x, 0.0]).collect(),
-                )
-                .expect("Bad shape"),
-                &Array1::from_vec(res.assignments),
-            )
-            .expect("Bad shape");
-            (n, silhouette, calinski_harabasz, davies_bouldin)
+            let inertia = utils::inertia(&features, &res.centroids.to_vec(), &res.assignments);
+            let davies_bouldin =
+                clustering::davies_bouldin_score(&features, &Array1::from_vec(res.assignments))
+                    .expect("Bad shape");
+            (n, silhouette, calinski_harabasz, davies_bouldin, inertia)
         })
-        .collect::<Vec<(u32, f64, f64, f64)>>();
+        .collect::<Vec<(u32, f64, f64, f64, f64)>>();
 
-    let silhouette_scores: Vec<f64> = scores.iter().map(|(_, s, _, _)| *s).collect();
-    let calinski_harabasz_scores: Vec<f64> = scores.iter().map(|(_, _, ch, _)| *ch).collect();
-    let davies_bouldin_scores: Vec<f64> = scores.iter().map(|(_, _, _, db)| *db).collect();
+    let silhouette_scores: Vec<f64> = scores.iter().map(|(_, s, _, _, _)| *s).collect();
+    let calinski_harabasz_scores: Vec<f64> = scores.iter().map(|(_, _, ch, _, _)| *ch).collect();
+    let davies_bouldin_scores: Vec<f64> = scores.iter().map(|(_, _, _, db, _)| *db).collect();
+    let inertia_scores: Vec<f64> = scores.iter().map(|(_, _, _, _, i)| *i).collect();
 
     // Apply normalization based on user choice
-    let (silhouette_weights, calinski_harabasz_weights, davies_bouldin_weights) =
+    let (silhouette_weights, calinski_harabasz_weights, davies_bouldin_weights, inertia_weights) =
         match arg.norm_method.as_str() {
             "probability" => (
                 utils::probability_norm(silhouette_scores.clone()),
                 utils::probability_norm(calinski_harabasz_scores.clone()),
                 utils::probability_norm(davies_bouldin_scores.clone()),
+                utils::probability_norm(inertia_scores.clone()),
             ),
             "minmax" => (
                 utils::min_max_norm(silhouette_scores.clone()),
                 utils::min_max_norm(calinski_harabasz_scores.clone()),
                 utils::min_max_norm_rev(davies_bouldin_scores.clone()),
+                utils::min_max_norm_rev(inertia_scores.clone()),
             ),
             "scale" => (
                 utils::scale_norm(silhouette_scores.clone()),
                 utils::scale_norm(calinski_harabasz_scores.clone()),
                 utils::scale_norm(davies_bouldin_scores.clone()),
+                utils::scale_norm(inertia_scores.clone()),
             ),
             "zscore" => (
                 utils::z_score_norm(silhouette_scores.clone()),
                 utils::z_score_norm(calinski_harabasz_scores.clone()),
                 utils::z_score_norm(davies_bouldin_scores.clone()),
+                utils::z_score_norm(inertia_scores.clone()),
             ),
             _ => panic!("Unsupported normalization method: {}", arg.norm_method),
         };
 
-    let n_values: Vec<u32> = scores.iter().map(|(n, _, _, _)| *n).collect();
+    let n_values: Vec<u32> = scores.iter().map(|(n, _, _, _, _)| *n).collect();
 
     let s_devi = utils::calculate_variance(silhouette_weights.clone());
     let c_devi = utils::calculate_variance(calinski_harabasz_weights.clone());
     let d_devi = utils::calculate_variance(davies_bouldin_weights.clone());
-    let ve = utils::probability_norm(vec![s_devi, c_devi, d_devi]);
-    println!("The weights : {:?}", ve);
+    let i_devi = utils::calculate_variance(inertia_weights.clone());
+    let ve = utils::probability_norm(vec![s_devi, c_devi, d_devi, i_devi]);
+    if !arg.quiet {
+        println!("The weights : {:?}", ve);
+    }
 
     let weights = utils::entropy_weight_method(
         &[
             silhouette_scores,
             calinski_harabasz_scores,
             davies_bouldin_scores,
+            inertia_scores,
         ],
         &[
             IndicatorType::Positive,
             IndicatorType::Positive,
             IndicatorType::Negative,
+            IndicatorType::Negative,
         ],
     );
-    println!("The entropy weights : {:?}", weights);
+    if !arg.quiet {
+        println!("The entropy weights : {:?}", weights);
+    }
 
     let total_scores_calculated: Vec<f64> = silhouette_weights
         .iter()
@@ -211,21 +454,44 @@ fn opt_best_n_state(arg: Nop) -> Result<(), Box<dyn std::error::Error>> {
         .map(|(w, w2)| w + w2 * weights[1])
         .zip(davies_bouldin_weights.iter())
         .map(|(w, w2)| w + w2 * weights[2])
+        .zip(inertia_weights.iter())
+        .map(|(w, w2)| w + w2 * weights[3])
         .collect();
 
+    if let Some(best) = (0..n_values.len())
+        .max_by(|&a, &b| total_scores_calculated[a].total_cmp(&total_scores_calculated[b]))
+    {
+        let (n, silhouette, calinski_harabasz, davies_bouldin, inertia) = scores[best];
+        println!(
+            "Recommended clusters: {} (silhouette={:.3}, CH={:.3}, DB={:.3}, inertia={:.3}, total={:.3})",
+            n,
+            silhouette,
+            calinski_harabasz,
+            davies_bouldin,
+            inertia,
+            total_scores_calculated[best]
+        );
+    }
+
     let mut df = DataFrame::new(vec![
         Column::new("n".into(), n_values),
         Column::new("silhouette_score".into(), silhouette_weights),
         Column::new("calinski_harabasz_score".into(), calinski_harabasz_weights),
         Column::new("davies_bouldin_score".into(), davies_bouldin_weights),
+        Column::new("inertia".into(), inertia_weights),
         Column::new("total_score".into(), total_scores_calculated),
     ])?;
 
     let file = std::fs::File::create(arg.output.clone())?;
-    CsvWriter::new(file)
-        .include_header(true)
-        .with_float_precision(Some(3))
-        .finish(&mut df)?;
+    match arg.format {
+        OutputFormat::Csv => CsvWriter::new(file)
+            .include_header(true)
+            .with_float_precision(Some(3))
+            .finish(&mut df)?,
+        OutputFormat::Parquet => {
+            ParquetWriter::new(file).finish(&mut df)?;
+        }
+    }
 
     println!("Scores dumped to {}", arg.output.display());
     Ok(())
@@ -236,6 +502,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match cli.command {
         Commands::Nop(arg) => opt_best_n_state(arg)?,
+        Commands::Fit(arg) => fit_model(arg)?,
+        Commands::Assign(arg) => assign_clusters(arg)?,
     }
 
     Ok(())
@@ -0,0 +1,84 @@
+use crate::{opt_best_n_state, Cli, Commands};
+use clap::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn write_input(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("makwei_{}_{}.csv", name, std::process::id()));
+    let mut content = String::from("wind\n");
+    for i in 0..30 {
+        content.push_str(&format!("{}\n", (i % 3) as f64 * 10.0 + (i as f64) * 0.01));
+    }
+    fs::write(&path, content).unwrap();
+    path
+}
+
+fn run(input: &Path, output: &Path, start: &str, end: &str) -> Result<(), String> {
+    let cli = Cli::parse_from([
+        "makwei",
+        "nop",
+        input.to_str().unwrap(),
+        "-s",
+        start,
+        "-e",
+        end,
+        "-o",
+        output.to_str().unwrap(),
+    ]);
+    let Commands::Nop(arg) = cli.command else {
+        unreachable!()
+    };
+    opt_best_n_state(arg).map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_single_k_range() {
+    let input = write_input("single_k");
+    let output = input.with_extension("out.csv");
+
+    run(&input, &output, "3", "3").unwrap();
+
+    let rows: Vec<String> = fs::read_to_string(&output)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect();
+    assert_eq!(rows.len(), 2); // header + one row
+    assert!(rows[1].starts_with("3,"));
+
+    fs::remove_file(input).ok();
+    fs::remove_file(output).ok();
+}
+
+#[test]
+fn test_invalid_range() {
+    let input = write_input("invalid_range");
+    let output = input.with_extension("out.csv");
+
+    assert!(run(&input, &output, "1", "4").is_err());
+    assert!(run(&input, &output, "5", "4").is_err());
+    assert!(!output.exists());
+
+    fs::remove_file(input).ok();
+}
+
+#[test]
+fn test_ndjson_input() {
+    let csv = write_input("ndjson");
+    let input = csv.with_extension("ndjson");
+    let lines: Vec<String> = fs::read_to_string(&csv)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|wind| format!("{{\"wind\":{}}}", wind))
+        .collect();
+    fs::write(&input, lines.join("\n")).unwrap();
+    let output = input.with_extension("out.csv");
+
+    run(&input, &output, "3", "3").unwrap();
+    assert_eq!(fs::read_to_string(&output).unwrap().lines().count(), 2);
+
+    fs::remove_file(csv).ok();
+    fs::remove_file(input).ok();
+    fs::remove_file(output).ok();
+}
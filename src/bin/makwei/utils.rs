@@ -1,4 +1,5 @@
 use crate::IndicatorType;
+use ndarray::Array2;
 
 /// 计算输入数值向量的方差。
 ///
@@ -71,6 +72,55 @@ pub fn z_score_norm(seq: Vec<f64>) -> Vec<f64> {
     seq.iter().map(|x| (x - mean) / std_dev).collect()
 }
 
+/// 计算聚类结果的簇内平方和（inertia），即每个样本到其所属质心的欧氏距离平方之和。
+///
+/// # 参数
+/// - `features`: 形状为 `(n_samples, n_features)` 的样本矩阵。
+/// - `centroids`: 按行展开的质心坐标，长度为 `k * n_features`。
+/// - `assignments`: 每个样本所属的簇编号。
+///
+/// # 返回值
+/// 返回一个f64类型的值，表示簇内平方和，越小说明簇越紧凑。
+pub fn inertia(features: &Array2<f64>, centroids: &[f64], assignments: &[usize]) -> f64 {
+    let n_features = features.ncols();
+    features
+        .outer_iter()
+        .zip(assignments)
+        .map(|(sample, &cluster)| {
+            let centroid = &centroids[cluster * n_features..(cluster + 1) * n_features];
+            sample
+                .iter()
+                .zip(centroid)
+                .map(|(x, c)| (x - c).powi(2))
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+/// 找出距离样本最近的质心。
+///
+/// # 参数
+/// - `sample`: 单个样本的特征向量。
+/// - `centroids`: 按行展开的质心坐标，每个质心的维度与 `sample` 相同。
+///
+/// # 返回值
+/// 返回最近质心的编号。
+pub fn nearest_centroid(sample: &[f64], centroids: &[f64]) -> usize {
+    centroids
+        .chunks_exact(sample.len())
+        .map(|centroid| {
+            sample
+                .iter()
+                .zip(centroid)
+                .map(|(x, c)| (x - c).powi(2))
+                .sum::<f64>()
+        })
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
 pub fn entropy_weight_method(data: &[Vec<f64>], types: &[IndicatorType]) -> Vec<f64> {
     let num_indicators = data.len();
     if num_indicators == 0 {
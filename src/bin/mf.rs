@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use dialoguer::{Confirm, Select};
 use fs_extra::dir::CopyOptions;
 use humansize::{format_size, BaseUnit, FormatSizeOptions};
@@ -7,7 +7,9 @@ use std::collections::HashMap;
 use std::fs;
 use std::io::{self};
 use std::path::PathBuf;
-use strsim::normalized_damerau_levenshtein;
+use std::sync::LazyLock;
+use strsim::{jaro, jaro_winkler, normalized_damerau_levenshtein};
+use unicode_normalization::UnicodeNormalization;
 use walkdir::WalkDir;
 
 #[derive(Parser)]
@@ -24,10 +26,39 @@ struct Args {
     #[arg(long, short, default_value_t = false)]
     create: bool,
 
-    /// the levenshtein threashhold
+    /// the similarity threashhold, between 0 and 1
     #[arg(long, short, default_value_t = 0.6)]
     threshold: f64,
 
+    /// the string similarity metric, damerau-levenshtein by default
+    #[arg(long, short, value_enum, default_value_t = Metric::Damerau)]
+    metric: Metric,
+
+    /// only print the planned moves and their total size, without moving or cleaning anything
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+
+}
+
+/// string similarity metrics, all scored between 0 and 1
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Metric {
+    /// normalized damerau-levenshtein distance
+    Damerau,
+    /// jaro similarity
+    Jaro,
+    /// jaro-winkler similarity, favors names sharing a prefix
+    JaroWinkler,
+}
+
+impl Metric {
+    fn similarity(self, a: &str, b: &str) -> f64 {
+        match self {
+            Metric::Damerau => normalized_damerau_levenshtein(a, b),
+            Metric::Jaro => jaro(a, b),
+            Metric::JaroWinkler => jaro_winkler(a, b),
+        }
+    }
 }
 
 fn main() -> io::Result<()> {
@@ -40,13 +71,15 @@ fn main() -> io::Result<()> {
     // 创建一个映射表，用于存储文件夹 B 中每个子文件夹的所有可能匹配
     let matches: HashMap<String, Vec<(String, usize)>> = src_folders
         .iter()
-        .map(|src_folder| (src_folder.clone(), find_possible_matches(src_folder, &dst_folders, args.threshold)))
+        .map(|src_folder| (src_folder.clone(), find_possible_matches(src_folder, &dst_folders, args.threshold, args.metric)))
         .collect();
 
 
     let opt = CopyOptions::default()
         .skip_exist(true);
 
+    let mut planned_bytes = 0u64;
+    let mut failed_merges: Vec<String> = Vec::new();
 
     matches.iter()
         .for_each(|(src_folder, match_as)|
@@ -67,12 +100,14 @@ fn main() -> io::Result<()> {
                         let src_full_path = format!("{}/{}", args.src, src_folder);
                         let dst_full_path = format!("{}/{}", args.dst, selected_match);
 
+                        if args.dry_run {
+                            planned_bytes += print_move_plan(&src_full_path, &dst_full_path);
+                            return;
+                        }
 
-                        let to_move = &extract_to_move(&src_full_path);
-                        move_files(&opt, &dst_full_path, to_move);
-
-
-                        clean(&src_full_path);
+                        if !merge_folder(&opt, &src_full_path, &dst_full_path) {
+                            failed_merges.push(src_full_path);
+                        }
                     }
                 } else if args.create || Confirm::new()
                     .with_prompt(format!("Did not find a match for folder [{}] in [{}] Create a new folder?", src_folder, args.dst))
@@ -80,28 +115,96 @@ fn main() -> io::Result<()> {
                     .expect("Failed to read input") {
                     let src_full_path = format!("{}/{}", args.src, src_folder);
                     let dst_full_path = format!("{}/{}", args.dst, src_folder);
+                    if args.dry_run {
+                        println!("Would create folder {dst_full_path}");
+                        planned_bytes += print_move_plan(&src_full_path, &dst_full_path);
+                        return;
+                    }
                     fs::create_dir(&dst_full_path).expect("Failed to create folder");
 
-                    let to_move = &extract_to_move(&src_full_path);
-                    move_files(&opt, &dst_full_path, to_move);
-                    clean(&src_full_path);
+                    if !merge_folder(&opt, &src_full_path, &dst_full_path) {
+                        failed_merges.push(src_full_path);
+                    }
                 }
             });
 
+    if args.dry_run {
+        println!("\nDry run: {} would be moved in total", format_size(planned_bytes, size_options()));
+    }
+
+    if !failed_merges.is_empty() {
+        eprintln!("\nFailed to fully merge {} folder(s), left in place:", failed_merges.len());
+        failed_merges.iter().for_each(|folder| eprintln!("  {folder}"));
+        std::process::exit(1);
+    }
+
     Ok(())
 }
 
+fn size_options() -> FormatSizeOptions {
+    FormatSizeOptions::default()
+        .decimal_zeroes(2)
+        .decimal_places(2)
+        .base_unit(BaseUnit::Byte)
+}
+
+/// print every file that would be moved from `src_full_path` into `dst_full_path`, returns the total bytes
+fn print_move_plan(src_full_path: &str, dst_full_path: &str) -> u64 {
+    let files: Vec<_> = WalkDir::new(src_full_path)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|en| en.file_type().is_file())
+        .collect();
+
+    let mut total = 0u64;
+    for file in &files {
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let relative = file.path().strip_prefix(src_full_path).unwrap_or(file.path());
+        let target = PathBuf::from(dst_full_path).join(relative);
+        // the real move uses skip_exist, so existing targets are left alone
+        if target.exists() {
+            println!("  {} -> {} (exists, skipped)", file.path().display(), target.display());
+            continue;
+        }
+        println!("  {} -> {} ({})", file.path().display(), target.display(), format_size(size, size_options()));
+        total += size;
+    }
+    println!("Would move {} files ({}) from {} to {}", files.len(), format_size(total, size_options()), src_full_path, dst_full_path);
+    total
+}
+
+
+/// move the content of `src_full_path` into `dst_full_path`, the source is only cleaned when every item moved.
+/// returns whether the merge succeeded
+fn merge_folder(opt: &CopyOptions, src_full_path: &String, dst_full_path: &String) -> bool {
+    let to_move = extract_to_move(src_full_path);
+    match move_files(opt, dst_full_path, &to_move) {
+        Ok(()) => {
+            clean(src_full_path);
+            true
+        }
+        Err(e) => {
+            eprintln!("\nFailed to merge {src_full_path} into {dst_full_path}: {e}");
+            // a moved item no longer exists in the source, a partially moved folder still does
+            let (moved, not_moved): (Vec<&PathBuf>, Vec<&PathBuf>) = to_move.iter()
+                .partition(|item| !item.exists());
+            eprintln!("Moved {} of {} item(s):", moved.len(), to_move.len());
+            moved.iter().for_each(|item| eprintln!("  [moved] {}", item.display()));
+            not_moved.iter().for_each(|item| eprintln!("  [not moved] {}", item.display()));
+            eprintln!("Skipped cleaning {src_full_path}");
+            false
+        }
+    }
+}
 
 /// move files
-fn move_files(opt: &CopyOptions, dst_full_path: &String, to_move: &[PathBuf]) {
+fn move_files(opt: &CopyOptions, dst_full_path: &String, to_move: &[PathBuf]) -> fs_extra::error::Result<()> {
     println!();
 
     let mut cur_file = String::new();
     let mut done = 0u64;
-    let size_opt = FormatSizeOptions::default()
-        .decimal_zeroes(2)
-        .decimal_places(2)
-        .base_unit(BaseUnit::Byte);
+    let size_opt = size_options();
     fs_extra::move_items_with_progress(to_move,
                                        dst_full_path, opt,
                                        |prog|
@@ -113,7 +216,7 @@ fn move_files(opt: &CopyOptions, dst_full_path: &String, to_move: &[PathBuf]) {
                                                });
                                                fs_extra::dir::TransitProcessResult::ContinueOrAbort
                                            })
-        .expect("Failed to merge folders");
+        .map(|_| ())
 }
 
 fn extract_to_move(src_full_path: &String) -> Vec<PathBuf> {
@@ -154,6 +257,11 @@ fn get_subfolders(folder: &str) -> Vec<String> {
         .collect()
 }
 
+static S_BRACKETS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\(\D*\d*\D+\d*\D*\)").unwrap());
+static M_BRACKETS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\D*\d*\D+\d*\D*]").unwrap());
+static L_BRACKETS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\D*\d*\D+\d*\D*}").unwrap());
+static UID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d{6,})").unwrap());
+
 trait RemoveBrackets {
     fn remove_s_brackets(&self) -> String;
     fn remove_m_brackets(&self) -> String;
@@ -162,18 +270,15 @@ trait RemoveBrackets {
 
 impl RemoveBrackets for str {
     fn remove_s_brackets(&self) -> String {
-        let re = Regex::new(r"\(\D*\d*\D+\d*\D*\)").unwrap();
-        re.replace_all(self, "").into_owned()
+        S_BRACKETS.replace_all(self, "").into_owned()
     }
 
     fn remove_m_brackets(&self) -> String {
-        let re = Regex::new(r"\[\D*\d*\D+\d*\D*]").unwrap();
-        re.replace_all(self, "").into_owned()
+        M_BRACKETS.replace_all(self, "").into_owned()
     }
 
     fn remove_l_brackets(&self) -> String {
-        let re = Regex::new(r"\{\D*\d*\D+\d*\D*}").unwrap();
-        re.replace_all(self, "").into_owned()
+        L_BRACKETS.replace_all(self, "").into_owned()
     }
 }
 
@@ -184,12 +289,7 @@ trait Uid {
 }
 impl Uid for str {
     fn uid(&self) -> Option<usize> {
-        let re = Regex::new(r"(\d{6,})").unwrap();
-        if re.find(self).is_some() {
-            Some(re.find(self).unwrap().as_str().parse::<usize>().unwrap())
-        } else {
-            None
-        }
+        UID.find(self).and_then(|m| m.as_str().parse::<usize>().ok())
     }
 
     fn eq_uid(&self, other: &str) -> bool {
@@ -201,22 +301,28 @@ impl Uid for str {
     }
 }
 
-fn eval_similarity(src: &str, dst: &str) -> f64 {
-    normalized_damerau_levenshtein(src, dst)
-        .max(normalized_damerau_levenshtein(src.remove_m_brackets()
-                                                .remove_l_brackets()
-                                                .remove_s_brackets().as_str(), dst))
-        .max(if src.eq_uid(dst) { 1. } else { 0. })
+/// NFKC-normalize and lowercase, so case and full-width/half-width forms compare equal
+fn normalize(name: &str) -> String {
+    name.nfkc().collect::<String>().to_lowercase()
+}
+
+fn eval_similarity(src: &str, dst: &str, metric: Metric) -> f64 {
+    let (src, dst) = (normalize(src), normalize(dst));
+    metric.similarity(&src, &dst)
+        .max(metric.similarity(src.remove_m_brackets()
+                                   .remove_l_brackets()
+                                   .remove_s_brackets().as_str(), &dst))
+        .max(if src.eq_uid(&dst) { 1. } else { 0. })
 }
 
 /// find possible matches for a given string in a list of strings
-fn find_possible_matches(src: &str, matches: &[String], threshold: f64) -> Vec<(String, usize)> {
+fn find_possible_matches(src: &str, matches: &[String], threshold: f64, metric: Metric) -> Vec<(String, usize)> {
     let mut possible_matches: Vec<_> = matches
         .iter()
-        .map(|dir| (dir.to_owned(), (eval_similarity(src, dir) * 100.) as usize))
+        .map(|dir| (dir.to_owned(), (eval_similarity(src, dir, metric) * 100.) as usize))
         .collect();
 
-    // 按 Levenshtein 距离从高到低排序
+    // 按相似度从高到低排序
     possible_matches.sort_by_key(|(_, score)| -(*score as isize));
 
 
@@ -225,3 +331,31 @@ fn find_possible_matches(src: &str, matches: &[String], threshold: f64) -> Vec<(
     possible_matches
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity_ignores_case_and_width() {
+        assert_eq!(eval_similarity("Game", "game", Metric::Damerau), 1.);
+        assert_eq!(eval_similarity("ＡＢＣ１２３", "abc123", Metric::Damerau), 1.);
+        assert_eq!(eval_similarity("作者（１２３４５６７）", "作者(1234567)", Metric::Damerau), 1.);
+    }
+
+    #[test]
+    fn test_similarity_over_thousands_of_names() {
+        let dst_folders: Vec<String> = (0..2000)
+            .map(|i| format!("[Group{}] Artist {} ({})", i % 7, i, 1_000_000 + i))
+            .collect();
+        let src_folders: Vec<String> = (0..10)
+            .map(|i| format!("Artist {} {{extra}} {}", i * 100, 1_000_000 + i * 100))
+            .collect();
+
+        for (i, src) in src_folders.iter().enumerate() {
+            let matches = find_possible_matches(src, &dst_folders, 0.6, Metric::Damerau);
+            // The UID shortcut must always rank the folder with the same ID first
+            assert_eq!(matches[0], (dst_folders[i * 100].clone(), 100));
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use clap::Parser;
 use rayon::prelude::*;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -15,49 +15,122 @@ struct Cli {
     /// Starting number for image renaming
     #[clap(short, long, default_value = "1")]
     start: u32,
+
+    /// Assets subdirectory of each target directory holding the images
+    #[clap(short, long, default_value = "images")]
+    images_dir: String,
+
+    /// Print the planned renames and markdown edits without writing anything
+    #[clap(short = 'n', long)]
+    dry_run: bool,
+
+    /// Number images continuously across all target directories instead of restarting at `--start`
+    #[clap(short, long)]
+    global: bool,
 }
 
+/// Matches Markdown `![alt](path)` and HTML `<img src="path">` image references
+const IMAGE_PATTERN: &str = r#"!\[(?P<alt>.*?)]\((?P<path>.*?)\)|(?P<pre><img\s[^>]*?src\s*=\s*["'])(?P<src>[^"']+)(?P<post>["'])"#;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Cli::parse();
     let parent_dir = args.parent_dir.canonicalize()?;
     let start_number = args.start;
+    let images_dir = normalize_reference(&args.images_dir)
+        .trim_end_matches('/')
+        .to_string();
 
     if !parent_dir.is_dir() {
         eprintln!("Error: {:?} is not a directory", parent_dir);
         std::process::exit(1);
     }
 
-    fs::read_dir(&parent_dir)
+    // Sorted so the global numbering does not depend on the directory listing order
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&parent_dir)
         .expect("Failed to read directory")
-        .par_bridge()
         .filter_map(|entry| entry.ok())
-        .for_each(|entry| {
-            let path = entry.path();
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
 
-            if path.is_dir() {
-                match process_target_directory(&path, start_number) {
-                    Ok(_) => println!("✅ Processed {}: {:?}", start_number, path),
-                    Err(e) => eprintln!("❌ Failed {}: {:?}: {}", start_number, path, e),
-                }
+    // Each directory gets a block of numbers `start..end`, laid out before anything is renamed
+    let blocks: Vec<(u32, Option<u32>)> = if args.global {
+        let counts: Vec<u32> = dirs
+            .par_iter()
+            .map(|dir| count_images(dir, &images_dir).unwrap_or(0))
+            .collect();
+        let mut next = start_number;
+        counts
+            .into_iter()
+            .map(|count| {
+                let block = (next, Some(next + count));
+                next += count;
+                block
+            })
+            .collect()
+    } else {
+        vec![(start_number, None); dirs.len()]
+    };
+
+    dirs.par_iter()
+        .zip(blocks)
+        .for_each(|(path, (start, end))| {
+            match process_target_directory(path, &images_dir, start, end, args.dry_run) {
+                Ok(()) => println!("✅ Processed {}: {:?}", start, path),
+                Err(e) => eprintln!("❌ Failed {}: {:?}: {}", start, path, e),
             }
         });
 
     Ok(())
 }
 
-/// Process a single target directory with specified starting number
-fn process_target_directory(dir: &Path, start: u32) -> Result<(), Box<dyn std::error::Error>> {
-    let (md_file, images_dir) = validate_directory(dir)?;
+/// Counts the existing images a target directory references, the size of its `--global` block
+fn count_images(dir: &Path, images_dir_name: &str) -> Result<u32, Box<dyn std::error::Error>> {
+    let (md_file, images_dir) = validate_directory(dir, images_dir_name)?;
+    let image_references = process_markdown_images(&fs::read_to_string(&md_file)?)?;
+    let referenced = referenced_images(&image_references, dir, &images_dir, images_dir_name);
+    Ok(referenced.iter().filter(|path| path.exists()).count() as u32)
+}
+
+/// Process a single target directory, numbering its images from `start`.
+///
+/// With an `end` the numbers must stay below it, see `rename_images`.
+fn process_target_directory(
+    dir: &Path,
+    images_dir_name: &str,
+    start: u32,
+    end: Option<u32>,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (md_file, images_dir) = validate_directory(dir, images_dir_name)?;
     let md_content = fs::read_to_string(&md_file)?;
 
     let image_references = process_markdown_images(&md_content)?;
-    let path_map = rename_images(&image_references, dir, &images_dir, start)?;
+    let referenced = referenced_images(&image_references, dir, &images_dir, images_dir_name);
+    for orphan in find_orphans(&referenced, &images_dir)? {
+        eprintln!("  ⚠️ Orphan: {:?}", orphan);
+    }
+
+    let path_map = rename_images(
+        &image_references,
+        dir,
+        &images_dir,
+        images_dir_name,
+        start,
+        end,
+        dry_run,
+    )?;
 
-    update_markdown_content(&md_file, &md_content, &path_map)
+    update_markdown_content(&md_file, &md_content, &path_map, dry_run)?;
+    Ok(())
 }
 
 /// Validates directory structure and returns required paths
-fn validate_directory(dir: &Path) -> Result<(PathBuf, PathBuf), &'static str> {
+fn validate_directory(
+    dir: &Path,
+    images_dir_name: &str,
+) -> Result<(PathBuf, PathBuf), &'static str> {
     if !dir.is_dir() {
         return Err("Invalid directory");
     }
@@ -79,7 +152,7 @@ fn validate_directory(dir: &Path) -> Result<(PathBuf, PathBuf), &'static str> {
         return Err("Multiple .md files found");
     }
 
-    let images_dir = dir.join("images");
+    let images_dir = dir.join(images_dir_name);
     if !images_dir.is_dir() {
         return Err("images directory not found");
     }
@@ -87,53 +160,170 @@ fn validate_directory(dir: &Path) -> Result<(PathBuf, PathBuf), &'static str> {
     Ok((md_files[0].clone(), images_dir))
 }
 
+/// Strips leading `./` segments and unifies separators of a relative reference
+fn normalize_reference(reference: &str) -> String {
+    let mut normalized = reference.trim().replace('\\', "/");
+    while let Some(stripped) = normalized.strip_prefix("./") {
+        normalized = stripped.to_string();
+    }
+    normalized
+}
+
+/// Resolves a reference to the image file it points at, if that file lives in the images directory
+fn resolve_reference(
+    reference: &str,
+    base_dir: &Path,
+    images_dir: &Path,
+    images_dir_name: &str,
+) -> Option<PathBuf> {
+    let path = Path::new(reference.trim());
+    if path.is_absolute() {
+        let images_dir = images_dir.canonicalize().ok()?;
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        return canonical.starts_with(&images_dir).then_some(canonical);
+    }
+
+    let normalized = normalize_reference(reference);
+    normalized
+        .starts_with(&format!("{}/", images_dir_name))
+        .then(|| base_dir.join(normalized))
+}
+
+/// Identifies an image file independently of how it was referenced, even after it was moved
+fn image_key(path: &Path) -> PathBuf {
+    let parent = path
+        .parent()
+        .and_then(|p| p.canonicalize().ok())
+        .unwrap_or_default();
+    parent.join(path.file_name().unwrap_or_default())
+}
+
+/// Collects the distinct image files the references point at, keyed by `image_key`
+fn referenced_images(
+    image_paths: &[String],
+    base_dir: &Path,
+    images_dir: &Path,
+    images_dir_name: &str,
+) -> HashSet<PathBuf> {
+    image_paths
+        .iter()
+        .filter_map(|path| resolve_reference(path, base_dir, images_dir, images_dir_name))
+        .map(|path| image_key(&path))
+        .collect()
+}
+
+/// Lists the files in the images directory that no reference points at
+fn find_orphans(
+    referenced: &HashSet<PathBuf>,
+    images_dir: &Path,
+) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut orphans: Vec<PathBuf> = fs::read_dir(images_dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !referenced.contains(&image_key(path)))
+        .collect();
+    orphans.sort();
+    Ok(orphans)
+}
+
 /// Extracts image paths from markdown content
 fn process_markdown_images(md_content: &str) -> Result<Vec<String>, regex::Error> {
-    let re = Regex::new(r"!\[.*?]\((.*?)\)")?;
+    let re = Regex::new(IMAGE_PATTERN)?;
     Ok(re
         .captures_iter(md_content)
-        .filter_map(|cap| cap.get(1))
+        .filter_map(|cap| cap.name("path").or_else(|| cap.name("src")))
         .map(|m| m.as_str().to_string())
         .collect())
 }
 
 /// Renames images with configurable starting number
+///
+/// With an `end`, fails before renaming anything when a number would reach it, e.g. because
+/// unrelated files already take names inside the block.
 fn rename_images(
     image_paths: &[String],
     base_dir: &Path,
     images_dir: &Path,
+    images_dir_name: &str,
     start: u32,
+    end: Option<u32>,
+    dry_run: bool,
 ) -> Result<HashMap<String, String>, std::io::Error> {
     let mut path_map = HashMap::new();
+    // An image referenced several times is renamed once, later references reuse its new name
+    let mut renamed: HashMap<PathBuf, PathBuf> = HashMap::new();
+    // Every name is settled before the first rename
+    let mut moves: Vec<(PathBuf, PathBuf)> = Vec::new();
 
     for original_path in image_paths {
-        if !original_path.starts_with("images/") {
+        if path_map.contains_key(original_path) {
             continue;
         }
-
-        let original_full_path = base_dir.join(original_path);
-        if !original_full_path.exists() {
-            eprintln!("  ⚠️ Missing: {:?}", original_full_path);
+        let Some(original_full_path) =
+            resolve_reference(original_path, base_dir, images_dir, images_dir_name)
+        else {
             continue;
-        }
+        };
+        let key = image_key(&original_full_path);
+
+        let new_full_path = match renamed.get(&key) {
+            Some(new_full_path) => new_full_path.clone(),
+            None => {
+                if !original_full_path.exists() {
+                    eprintln!("  ⚠️ Missing: {:?}", original_full_path);
+                    continue;
+                }
 
-        let extension = original_full_path
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("");
+                let extension = original_full_path
+                    .extension()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("");
 
-        let mut new_number = start + path_map.len() as u32;
-        let mut new_filename = format!("{}.{}", new_number, extension);
-        let mut new_full_path = images_dir.join(&new_filename);
+                let mut new_number = start + renamed.len() as u32;
+                let mut new_filename = format!("{}.{}", new_number, extension);
+                let mut new_full_path = images_dir.join(&new_filename);
 
-        while new_full_path.exists() {
-            new_number += 1;
-            new_filename = format!("{}.{}", new_number, extension);
-            new_full_path = images_dir.join(&new_filename);
-        }
+                // Planned names count as taken too, an image already carrying its name keeps it
+                while (new_full_path.exists() && image_key(&new_full_path) != key)
+                    || renamed.values().any(|p| p == &new_full_path)
+                {
+                    new_number += 1;
+                    new_filename = format!("{}.{}", new_number, extension);
+                    new_full_path = images_dir.join(&new_filename);
+                }
+                if let Some(end) = end.filter(|&end| new_number >= end) {
+                    return Err(std::io::Error::other(format!(
+                        "no free number in {}..{} for {:?}",
+                        start, end, original_full_path
+                    )));
+                }
+
+                if image_key(&new_full_path) != key {
+                    moves.push((original_full_path.clone(), new_full_path.clone()));
+                }
+                renamed.insert(key, new_full_path.clone());
+                new_full_path
+            }
+        };
 
-        fs::rename(&original_full_path, &new_full_path)?;
-        path_map.insert(original_path.clone(), format!("images/{}", new_filename));
+        let new_reference = if Path::new(original_path.trim()).is_absolute() {
+            image_key(&new_full_path).to_string_lossy().into_owned()
+        } else {
+            format!(
+                "{}/{}",
+                images_dir_name,
+                new_full_path.file_name().unwrap().to_string_lossy()
+            )
+        };
+        path_map.insert(original_path.clone(), new_reference);
+    }
+
+    for (from, to) in &moves {
+        if dry_run {
+            println!("  🔁 {:?} -> {:?}", from, to);
+        } else {
+            fs::rename(from, to)?;
+        }
     }
 
     Ok(path_map)
@@ -144,18 +334,35 @@ fn update_markdown_content(
     md_file: &Path,
     md_content: &str,
     path_map: &HashMap<String, String>,
+    dry_run: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let re = Regex::new(r"!\[(?P<alt>.*?)]\((?P<path>.*?)\)")?;
+    let re = Regex::new(IMAGE_PATTERN)?;
+    let edited = |original: String, replacement: String| {
+        if dry_run && original != replacement {
+            println!("  ✏️ {} -> {}", original, replacement);
+        }
+        replacement
+    };
     let new_content = re.replace_all(md_content, |caps: &regex::Captures| {
-        let alt = &caps["alt"];
-        let path = &caps["path"];
+        let original = caps.get(0).unwrap().as_str().to_string();
+        if let Some(path) = caps.name("path") {
+            return match path_map.get(path.as_str()) {
+                Some(new_path) => edited(original, format!("![{}]({})", &caps["alt"], new_path)),
+                None => original,
+            };
+        }
 
-        match path_map.get(path) {
-            Some(new_path) => format!("![{}]({})", alt, new_path),
-            None => caps.get(0).unwrap().as_str().to_string(),
+        match path_map.get(&caps["src"]) {
+            Some(new_path) => edited(
+                original,
+                format!("{}{}{}", &caps["pre"], new_path, &caps["post"]),
+            ),
+            None => original,
         }
     });
 
-    fs::write(md_file, new_content.as_bytes())?;
+    if !dry_run {
+        fs::write(md_file, new_content.as_bytes())?;
+    }
     Ok(())
 }
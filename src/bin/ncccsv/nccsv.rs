@@ -1,13 +1,49 @@
 mod utils;
 
 use crate::utils::extract_locations;
-use clap::{arg, Args as ClapArgs, Parser, Subcommand};
+use chrono::NaiveDate;
+use clap::{arg, Args as ClapArgs, Parser, Subcommand, ValueEnum};
 use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
 use ndarray::prelude::*;
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+/// Grid cells contributing to a point, as `(lat_idx, lon_idx, weight)`.
+type Samples = Vec<(usize, usize, f64)>;
+
+/// Extracted values, as `(internal_ts, point_idx, value)`.
+type Rows = Vec<(i64, usize, f64)>;
+
+/// How a point between grid cells is sampled.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+enum Interp {
+    /// Use the closest grid cell.
+    #[default]
+    Nearest,
+    /// Weight the four surrounding grid cells by distance.
+    Bilinear,
+}
+
+/// Period that extracted values are bucketed into by `--resample`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Resample {
+    Hourly,
+    Daily,
+    Monthly,
+}
+
+/// How the values within one resampling period are combined.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+enum Agg {
+    #[default]
+    Mean,
+    Max,
+    Min,
+    Sum,
+}
+
 struct Point {
     lon: f32,
     lat: f32,
@@ -17,23 +53,86 @@ impl Point {
     fn new(lon: f32, lat: f32) -> Self {
         Point { lon, lat }
     }
-    fn get_nearest_sample(&self, lat_seq: Vec<f32>, lon_seq: Vec<f32>) -> (usize, usize) {
-        let lat_idx = lat_seq
-            .par_iter()
-            .enumerate()
-            .min_by_key(|&(_idx, &val_from_seq)| (val_from_seq - self.lat).abs().to_bits())
-            .map(|(index, _)| index)
+    fn get_nearest_sample(&self, lat_seq: &[f32], lon_seq: &[f32]) -> (usize, usize) {
+        let lat_idx = nearest_index(lat_seq, self.lat)
             .expect("lat_seq should not be empty and must contain valid float values.");
 
-        let lon_idx = lon_seq
-            .par_iter()
-            .enumerate()
-            .min_by_key(|&(_idx, &val_from_seq)| (val_from_seq - self.lon).abs().to_bits())
-            .map(|(index, _)| index)
+        let lon_idx = nearest_index(lon_seq, self.lon)
             .expect("lon_seq should not be empty and must contain valid float values.");
 
         (lat_idx, lon_idx)
     }
+
+    /// Returns the four grid cells surrounding the point with their bilinear weights.
+    ///
+    /// Errors if the point lies outside the grid rather than extrapolating. Cells with
+    /// zero weight are dropped so a missing value there can't leak into the result.
+    fn get_bilinear_samples(&self, lat_seq: &[f32], lon_seq: &[f32]) -> Result<Samples, String> {
+        let (lat_idx, lat_t) = bracket(lat_seq, self.lat)
+            .ok_or_else(|| format!("Latitude {} is outside the grid", self.lat))?;
+        let (lon_idx, lon_t) = bracket(lon_seq, self.lon)
+            .ok_or_else(|| format!("Longitude {} is outside the grid", self.lon))?;
+
+        Ok([
+            (lat_idx, lon_idx, (1.0 - lat_t) * (1.0 - lon_t)),
+            (lat_idx + 1, lon_idx, lat_t * (1.0 - lon_t)),
+            (lat_idx, lon_idx + 1, (1.0 - lat_t) * lon_t),
+            (lat_idx + 1, lon_idx + 1, lat_t * lon_t),
+        ]
+        .into_iter()
+        .filter(|&(_, _, weight)| weight > 0.0)
+        .collect())
+    }
+
+    fn get_samples(
+        &self,
+        lat_seq: &[f32],
+        lon_seq: &[f32],
+        interp: Interp,
+    ) -> Result<Samples, String> {
+        match interp {
+            Interp::Nearest => {
+                let (lat_idx, lon_idx) = self.get_nearest_sample(lat_seq, lon_seq);
+                Ok(vec![(lat_idx, lon_idx, 1.0)])
+            }
+            Interp::Bilinear => self.get_bilinear_samples(lat_seq, lon_seq),
+        }
+    }
+}
+
+/// Coordinate sequences at least this long are searched in parallel; below it the rayon
+/// overhead outweighs the work.
+const PARALLEL_SEARCH_THRESHOLD: usize = 10_000;
+
+/// Index of the element of `seq` closest to `target`, or `None` if `seq` is empty.
+///
+/// Distances are ordered with `total_cmp`, so a NaN coordinate (whose distance is NaN)
+/// sorts after every real distance and is only picked when nothing else is available.
+fn nearest_index(seq: &[f32], target: f32) -> Option<usize> {
+    let distance = |val: &f32| (val - target).abs();
+    if seq.len() < PARALLEL_SEARCH_THRESHOLD {
+        seq.iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(index, _)| index)
+    } else {
+        seq.par_iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Finds `i` with `x` between `seq[i]` and `seq[i + 1]` (ascending or descending),
+/// along with the fractional position of `x` in that interval.
+fn bracket(seq: &[f32], x: f32) -> Option<(usize, f64)> {
+    seq.windows(2)
+        .position(|w| (w[0] <= x && x <= w[1]) || (w[1] <= x && x <= w[0]))
+        .map(|i| {
+            let (a, b) = (seq[i], seq[i + 1]);
+            let t = if a == b { 0.0 } else { (x - a) / (b - a) };
+            (i, t as f64)
+        })
 }
 
 /// Command Line Interface (CLI) for NetCDF data processing.
@@ -59,13 +158,16 @@ enum Commands {
     /// longitude. The aggregated data is then written to a CSV file.
     Extract(ExtractArgs),
 
-    /// Calculates and displays the average value of a specified variable from NetCDF file(s).
+    /// Calculates statistics of a specified variable from NetCDF file(s).
+    ///
+    /// The statistics are printed and also written to the output CSV, one row
+    /// per file when the input is a directory.
     Probe(ProbeArgs),
 }
 
 #[derive(ClapArgs, Debug)]
 struct ProbeArgs {
-    /// Path to the input NetCDF file.
+    /// Path to the input NetCDF file or directory containing .nc files.
     input: PathBuf,
 
     /// Path to the output CSV file.
@@ -83,6 +185,10 @@ struct ProbeArgs {
     /// Name of the variable to probe from the NetCDF file.
     #[arg(short, long, default_value = "wind")]
     variable: String,
+
+    /// Index of the vertical level to select for 4-D `(time, level, lat, lon)` variables.
+    #[arg(long)]
+    level: Option<usize>,
 }
 
 /// Arguments for the 'extract' subcommand.
@@ -96,24 +202,108 @@ struct ExtractArgs {
     output: PathBuf,
 
     /// Latitude for data extraction (in degrees_north).
-    #[arg(short = 'a', long, required = true)]
-    lat: f32,
+    #[arg(
+        short = 'a',
+        long,
+        required_unless_present = "points",
+        requires = "lon"
+    )]
+    lat: Option<f32>,
 
     /// Longitude for data extraction (in degrees_east).
-    #[arg(short, long, required = true)]
-    lon: f32,
+    #[arg(short, long, required_unless_present = "points", requires = "lat")]
+    lon: Option<f32>,
+
+    /// CSV of `name,lat,lon` rows to extract in one pass, written as
+    /// `timestamp,point_name,value` rows.
+    #[arg(long, conflicts_with_all = ["lat", "lon"])]
+    points: Option<PathBuf>,
 
     /// Name of the variable to extract from the NetCDF file.
     #[arg(short, long, default_value = "wind")]
     variable: String,
+
+    /// How to sample the variable at the requested point.
+    #[arg(long, value_enum, default_value_t = Interp::Nearest)]
+    interp: Interp,
+
+    /// Index of the vertical level to select for 4-D `(time, level, lat, lon)` variables.
+    #[arg(long)]
+    level: Option<usize>,
+
+    /// Only keep timestamps on or after this date (YYYY-MM-DD, inclusive).
+    #[arg(long)]
+    start: Option<NaiveDate>,
+
+    /// Only keep timestamps on or before this date (YYYY-MM-DD, inclusive,
+    /// i.e. up to but excluding midnight of the following day).
+    #[arg(long)]
+    end: Option<NaiveDate>,
+
+    /// Aggregate values into one row per hour, day or month (per point), stamped with the
+    /// start of the period. Without it the file's native timestep is kept.
+    #[arg(long, value_enum)]
+    resample: Option<Resample>,
+
+    /// How values within a resampling period are combined.
+    #[arg(long, value_enum, default_value_t = Agg::Mean, requires = "resample")]
+    agg: Agg,
 }
 
+/// Reads the weighted, unpacked time series of `data_var` at one point.
+fn read_point_series(
+    data_var: &netcdf::Variable,
+    layout: &utils::DimLayout,
+    packing: &utils::Packing,
+    samples: &Samples,
+    file_path: &Path,
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let dims = data_var.dimensions();
+    let lat_dim_len = dims[layout.lat].len();
+    let lon_dim_len = dims[layout.lon].len();
+
+    let mut values_array: Vec<f64> = Vec::new();
+    for &(lat_idx, lon_idx, weight) in samples {
+        if lat_idx >= lat_dim_len {
+            return Err(format!(
+                "Latitude index {} out of bounds for {} (lat_dim_len: {})",
+                lat_idx,
+                file_path.display(),
+                lat_dim_len
+            )
+            .into());
+        }
+        if lon_idx >= lon_dim_len {
+            return Err(format!(
+                "Longitude index {} out of bounds for {} (lon_dim_len: {})",
+                lon_idx,
+                file_path.display(),
+                lon_dim_len
+            )
+            .into());
+        }
+
+        let series = data_var
+            .get_values::<f64, _>(layout.point_extents(lat_idx, lon_idx))
+            .map_err(|e| format!("Failed to read data from {}: {}", file_path.display(), e))?;
+        if values_array.is_empty() {
+            values_array = vec![0.0; series.len()];
+        }
+        values_array
+            .iter_mut()
+            .zip(series)
+            .for_each(|(acc, raw)| *acc += weight * packing.unpack(raw));
+    }
+    Ok(values_array)
+}
+
+/// Extracts every point from one file as `(internal_ts, point_idx, value)` rows.
 fn process_file(
     file_path: &Path,
     args: &ExtractArgs,
-    point: &Point,
-    cached_indices_arc: &Arc<Mutex<Option<(usize, usize)>>>,
-) -> Result<Vec<(i64, f64)>, Box<dyn std::error::Error>> {
+    points: &[(String, Point)],
+    cached_samples_arc: &Arc<Mutex<HashMap<String, Samples>>>,
+) -> Result<Rows, Box<dyn std::error::Error>> {
     let dataset = netcdf::open(file_path).map_err(|e| {
         format!(
             "Failed to open NetCDF file '{}': {}",
@@ -122,26 +312,35 @@ fn process_file(
         )
     })?;
 
-    let (nearest_lat_idx, nearest_lon_idx);
-
-    let mut indices_opt_guard = cached_indices_arc
-        .lock()
-        .map_err(|e| format!("Mutex for cached_indices poisoned: {}", e))?;
-
-    if let Some(cached_idxs) = *indices_opt_guard {
-        nearest_lat_idx = cached_idxs.0;
-        nearest_lon_idx = cached_idxs.1;
-        drop(indices_opt_guard); // Release lock early
-    } else {
-        let (lat_seq, lon_seq) = utils::extract_locations(&dataset)?;
-
-        let (idx_lat, idx_lon) = point.get_nearest_sample(lat_seq, lon_seq);
+    let samples_per_point: Vec<Samples> = {
+        let mut samples_guard = cached_samples_arc
+            .lock()
+            .map_err(|e| format!("Mutex for cached_samples poisoned: {}", e))?;
+
+        // Filled only once every point succeeded, a partial map would be reused by later files
+        if samples_guard.is_empty() {
+            let (lat_seq, lon_seq) = utils::extract_locations(&dataset)?;
+            let mut computed = HashMap::with_capacity(points.len());
+            for (name, point) in points {
+                let samples = point
+                    .get_samples(&lat_seq, &lon_seq, args.interp)
+                    .map_err(|e| format!("Point '{}': {}", name, e))?;
+                computed.insert(name.clone(), samples);
+            }
+            *samples_guard = computed;
+        }
 
-        *indices_opt_guard = Some((idx_lat, idx_lon));
-        nearest_lat_idx = idx_lat;
-        nearest_lon_idx = idx_lon;
+        points
+            .iter()
+            .map(|(name, _)| {
+                samples_guard
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("No cached samples for point '{}'", name))
+            })
+            .collect::<Result<_, _>>()?
         // MutexGuard is dropped automatically here when it goes out of scope
-    }
+    };
 
     let time_var = dataset
         .variable("time")
@@ -154,72 +353,67 @@ fn process_file(
         )
     })?;
 
-    let dims = data_var.dimensions();
-    if dims.len() < 3 {
-        return Err(format!(
-            "Variable '{}' in {} has insufficient dimensions (expected >=3, got {})",
+    let layout = utils::DimLayout::locate(data_var.dimensions(), args.level).map_err(|e| {
+        format!(
+            "Variable '{}' in {}: {}",
             args.variable,
             file_path.display(),
-            dims.len()
-        )
-        .into());
-    }
-
-    // Assuming dimensions are (time, lat, lon) or similar
-    let lat_dim_idx = dims
-        .iter()
-        .position(|d| d.name().to_lowercase() == "lat" || d.name().to_lowercase() == "latitude")
-        .unwrap_or(1); // Fallback to index 1 if not named 'lat'/'latitude'
-    let lon_dim_idx = dims
-        .iter()
-        .position(|d| d.name().to_lowercase() == "lon" || d.name().to_lowercase() == "longitude")
-        .unwrap_or(2); // Fallback to index 2
-
-    let lat_dim_len = dims.get(lat_dim_idx).map_or(0, |d| d.len());
-    let lon_dim_len = dims.get(lon_dim_idx).map_or(0, |d| d.len());
-
-    if nearest_lat_idx >= lat_dim_len {
-        return Err(format!(
-            "Latitude index {} out of bounds for {} (lat_dim_len: {})",
-            nearest_lat_idx,
-            file_path.display(),
-            lat_dim_len
-        )
-        .into());
-    }
-    if nearest_lon_idx >= lon_dim_len {
-        return Err(format!(
-            "Longitude index {} out of bounds for {} (lon_dim_len: {})",
-            nearest_lon_idx,
-            file_path.display(),
-            lon_dim_len
+            e
         )
-        .into());
-    }
-
-    let values_array = data_var
-        .get_values::<f64, _>((.., nearest_lat_idx, nearest_lon_idx)) // This slicing assumes (time, lat, lon) or (..., lat, lon)
-        .map_err(|e| format!("Failed to read data from {}: {}", file_path.display(), e))?;
+    })?;
+    let packing = utils::Packing::from_variable(&data_var)?;
 
     let time_values_array = time_var
         .get_values::<f64, _>(..)
         .map_err(|e| format!("Failed to read 'time' from {}: {}", file_path.display(), e))?;
+    let time_units = match time_var.attribute_value("units") {
+        Some(units) => {
+            let units: String = units?
+                .try_into()
+                .map_err(|e| format!("Invalid 'time' units in {}: {}", file_path.display(), e))?;
+            utils::TimeUnits::parse(&units)?
+        }
+        None => {
+            eprintln!(
+                "Warning: 'time' in {} has no units attribute, assuming hours since 1900-01-01",
+                file_path.display()
+            );
+            utils::TimeUnits::default_hours_since_1900()
+        }
+    };
+    let timestamps: Vec<i64> = time_values_array
+        .iter()
+        .map(|&raw_time| time_units.to_internal_seconds(raw_time))
+        .collect();
 
-    if values_array.len() != time_values_array.len() {
-        return Err(format!(
-            "Mismatch in data points/timestamps in {} ({} vs {})",
-            file_path.display(),
-            values_array.len(),
-            time_values_array.len()
-        )
-        .into());
-    }
-
-    let mut file_data = Vec::with_capacity(values_array.len());
-    for (idx, &value) in values_array.iter().enumerate() {
-        let raw_time = time_values_array[idx];
+    let start_ts = args.start.map(utils::date_to_internal_seconds);
+    let end_ts = args
+        .end
+        .and_then(|end| end.succ_opt())
+        .map(utils::date_to_internal_seconds);
+    let in_range = |ts: i64| start_ts.is_none_or(|s| ts >= s) && end_ts.is_none_or(|e| ts < e);
+
+    let mut file_data = Vec::with_capacity(timestamps.len() * points.len());
+    for (point_idx, samples) in samples_per_point.iter().enumerate() {
+        let values_array = read_point_series(&data_var, &layout, &packing, samples, file_path)?;
+
+        if values_array.len() != timestamps.len() {
+            return Err(format!(
+                "Mismatch in data points/timestamps in {} ({} vs {})",
+                file_path.display(),
+                values_array.len(),
+                timestamps.len()
+            )
+            .into());
+        }
 
-        file_data.push((raw_time as i64, value));
+        file_data.extend(
+            timestamps
+                .iter()
+                .zip(values_array)
+                .filter(|(&ts, _)| in_range(ts))
+                .map(|(&ts, value)| (ts, point_idx, value)),
+        );
     }
 
     Ok(file_data)
@@ -228,8 +422,8 @@ fn process_file(
 fn aggregate_data_from_files(
     input_files: &[PathBuf],
     args: &ExtractArgs, // Changed from Args to ExtractArgs
-    point: &Point,
-) -> Vec<(i64, f64)> {
+    points: &[(String, Point)],
+) -> Rows {
     let pb = ProgressBar::new(input_files.len() as u64);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -240,13 +434,13 @@ fn aggregate_data_from_files(
             .progress_chars("#>-")
     );
 
-    let cached_indices = Arc::new(Mutex::new(None::<(usize, usize)>));
+    let cached_samples = Arc::new(Mutex::new(HashMap::<String, Samples>::new()));
 
     input_files
         .par_iter()
         .progress_with(pb)
         .filter_map(
-            |file_path| match process_file(file_path, args, point, &cached_indices) {
+            |file_path| match process_file(file_path, args, points, &cached_samples) {
                 Ok(data_from_file) => Some(data_from_file),
                 Err(e) => {
                     eprintln!("Error processing {}: {}", file_path.display(), e);
@@ -255,50 +449,87 @@ fn aggregate_data_from_files(
             },
         )
         .flatten()
-        .collect::<Vec<(i64, f64)>>()
+        .collect::<Rows>()
 }
 
 fn handle_extract_command(args: &ExtractArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if let (Some(start), Some(end)) = (args.start, args.end) {
+        if start > end {
+            return Err(format!("--start {} is after --end {}", start, end).into());
+        }
+    }
+
     let input_files = utils::collect_input_files(&args.input)?;
     // collect_input_files is expected to return an Err if no suitable files are found,
     // so an explicit check for input_files.is_empty() is not performed here.
 
-    let point = Point::new(args.lon, args.lat);
-
-    let mut all_data = aggregate_data_from_files(&input_files, args, &point);
+    let points: Vec<(String, Point)> = match (&args.points, args.lat, args.lon) {
+        (Some(points_path), _, _) => utils::read_points(points_path)?
+            .into_iter()
+            .map(|spec| (spec.name, Point::new(spec.lon, spec.lat)))
+            .collect(),
+        (None, Some(lat), Some(lon)) => vec![(String::new(), Point::new(lon, lat))],
+        _ => return Err("Either --points or both --lat and --lon are required".into()),
+    };
+
+    let mut all_data = aggregate_data_from_files(&input_files, args, &points);
+    if let Some(period) = args.resample {
+        all_data = utils::resample_rows(&all_data, period, args.agg);
+    }
 
-    utils::write_data_to_csv(&args.output, &args.variable, &mut all_data)?;
+    if args.points.is_some() {
+        let names: Vec<&str> = points.iter().map(|(name, _)| name.as_str()).collect();
+        utils::write_long_data_to_csv(&args.output, &names, &mut all_data)?;
+    } else {
+        let mut all_data: Vec<(i64, f64)> = all_data
+            .into_iter()
+            .map(|(ts, _, value)| (ts, value))
+            .collect();
+        utils::write_data_to_csv(&args.output, &args.variable, &mut all_data)?;
+    }
 
     Ok(())
 }
 
-fn handle_probe_command(args: &ProbeArgs) -> Result<(), Box<dyn std::error::Error>> {
-    if args.input.is_dir() {
-        return Err(format!(
-            "Input path is not a valid file or directory: {}",
-            args.input.display()
-        )
-        .into());
-    }
-
-    let point = Point::new(args.lon, args.lat);
+/// Summary statistics of one probed file; the `Option`s are `None` without finite data.
+struct ProbeStats {
+    count: usize,
+    finite_count: usize,
+    mean: Option<f64>,
+    std: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
 
-    let dataset = netcdf::open(&args.input)?;
+fn probe_file(
+    file_path: &Path,
+    args: &ProbeArgs,
+    point: &Point,
+) -> Result<ProbeStats, Box<dyn std::error::Error>> {
+    let dataset = netcdf::open(file_path)?;
 
     let (lat_seq, lon_seq) = extract_locations(&dataset)?;
 
-    let (nearest_lat_idx, nearest_lon_idx) = point.get_nearest_sample(lat_seq, lon_seq);
+    let (nearest_lat_idx, nearest_lon_idx) = point.get_nearest_sample(&lat_seq, &lon_seq);
+
+    let data_var = dataset
+        .variable(args.variable.as_str())
+        .ok_or("Variable not found")?;
+    let layout = utils::DimLayout::locate(data_var.dimensions(), args.level)?;
+
+    let packing = utils::Packing::from_variable(&data_var)?;
 
     let arr = Array1::from(
-        dataset
-            .variable(args.variable.as_str())
-            .ok_or("Variable not found")?
-            .get_values::<f64, _>((.., nearest_lat_idx, nearest_lon_idx))?,
-    );
+        data_var.get_values::<f64, _>(layout.point_extents(nearest_lat_idx, nearest_lon_idx))?,
+    )
+    .mapv(|raw| packing.unpack(raw));
 
     println!(
-        "Statistics for variable '{}' at point (Lat: {:.2}, Lon: {:.2}):",
-        args.variable, args.lat, args.lon
+        "Statistics for variable '{}' in {} at point (Lat: {:.2}, Lon: {:.2}):",
+        args.variable,
+        file_path.display(),
+        args.lat,
+        args.lon
     );
     println!("  Total data points retrieved: {}", arr.len());
 
@@ -311,6 +542,15 @@ fn handle_probe_command(args: &ProbeArgs) -> Result<(), Box<dyn std::error::Erro
 
     println!("  Finite data points: {}", finite_values.len());
 
+    let mut stats = ProbeStats {
+        count: arr.len(),
+        finite_count: finite_values.len(),
+        mean: None,
+        std: None,
+        min: None,
+        max: None,
+    };
+
     if finite_values.is_empty() {
         println!("  No finite data points available to calculate statistics.");
     } else {
@@ -338,7 +578,60 @@ fn handle_probe_command(args: &ProbeArgs) -> Result<(), Box<dyn std::error::Erro
         println!("  Std Deviation:  {:.4}", std_dev_val);
         println!("  Minimum:        {:.4}", min_val);
         println!("  Maximum:        {:.4}", max_val);
+
+        stats.mean = Some(mean_val);
+        stats.std = Some(std_dev_val);
+        stats.min = Some(min_val);
+        stats.max = Some(max_val);
+    }
+    Ok(stats)
+}
+
+fn handle_probe_command(args: &ProbeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut input_files = utils::collect_input_files(&args.input)?;
+    input_files.sort();
+
+    let point = Point::new(args.lon, args.lat);
+
+    let mut wtr = csv::Writer::from_path(&args.output)?;
+    wtr.write_record([
+        "file",
+        "variable",
+        "lat",
+        "lon",
+        "count",
+        "finite_count",
+        "mean",
+        "std",
+        "min",
+        "max",
+    ])?;
+
+    let fmt_stat = |v: Option<f64>| v.map_or_else(String::new, |v| format!("{:.4}", v));
+    for file_path in &input_files {
+        let stats = match probe_file(file_path, args, &point) {
+            Ok(stats) => stats,
+            Err(e) => {
+                eprintln!("Error probing {}: {}", file_path.display(), e);
+                continue;
+            }
+        };
+        wtr.write_record([
+            file_path.display().to_string(),
+            args.variable.clone(),
+            args.lat.to_string(),
+            args.lon.to_string(),
+            stats.count.to_string(),
+            stats.finite_count.to_string(),
+            fmt_stat(stats.mean),
+            fmt_stat(stats.std),
+            fmt_stat(stats.min),
+            fmt_stat(stats.max),
+        ])?;
     }
+
+    wtr.flush()?;
+    println!("Statistics written to {}", args.output.display());
     Ok(())
 }
 
@@ -1,9 +1,11 @@
-use chrono::{Duration, NaiveDate, NaiveDateTime};
+use crate::{Agg, Resample};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use csv::Writer;
 use rayon::prelude::ParallelSliceMut;
+use std::collections::{BTreeMap, HashSet};
 use std::error::Error;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const BASE_DATETIME_NAIVE: NaiveDateTime = NaiveDate::from_ymd_opt(1900, 1, 1)
     .expect("Invalid base year, month, or day for NaiveDate")
@@ -11,12 +13,129 @@ const BASE_DATETIME_NAIVE: NaiveDateTime = NaiveDate::from_ymd_opt(1900, 1, 1)
     .expect("Invalid base hour, minute, or second for NaiveDate");
 
 /// Formats internal seconds representation (seconds since 1900-01-01) to a timestamp string.
-pub fn hours_to_timestamp_string(hours: i64) -> String {
-    (BASE_DATETIME_NAIVE + Duration::hours(hours))
+pub fn seconds_to_timestamp_string(seconds: i64) -> String {
+    (BASE_DATETIME_NAIVE + Duration::seconds(seconds))
         .format("%Y-%m-%d %H:%M:%S")
         .to_string()
 }
 
+/// Converts midnight of `date` to the internal seconds-since-1900 representation.
+pub fn date_to_internal_seconds(date: NaiveDate) -> i64 {
+    (date.and_time(NaiveTime::MIN) - BASE_DATETIME_NAIVE).num_seconds()
+}
+
+/// Truncates internal seconds to the start of the enclosing hour, day or month.
+pub fn truncate_to_period(seconds: i64, period: Resample) -> i64 {
+    let datetime = BASE_DATETIME_NAIVE + Duration::seconds(seconds);
+    let date = datetime.date();
+    let start = match period {
+        Resample::Hourly => date.and_hms_opt(datetime.hour(), 0, 0),
+        Resample::Daily => date.and_hms_opt(0, 0, 0),
+        Resample::Monthly => date
+            .with_day(1)
+            .and_then(|first| first.and_hms_opt(0, 0, 0)),
+    }
+    .expect("Period start is always a valid datetime");
+    (start - BASE_DATETIME_NAIVE).num_seconds()
+}
+
+/// Aggregates `(internal_ts, point_idx, value)` rows into one row per period and point.
+///
+/// NaN values (e.g. masked cells) are left out of the aggregate; a period with only NaN
+/// values stays NaN.
+pub fn resample_rows(
+    data: &[(i64, usize, f64)],
+    period: Resample,
+    agg: Agg,
+) -> Vec<(i64, usize, f64)> {
+    // (sum, min, max, count) of the finite values per bucket
+    let mut buckets: BTreeMap<(i64, usize), (f64, f64, f64, usize)> = BTreeMap::new();
+    for &(internal_ts, point_idx, value) in data {
+        let bucket = buckets
+            .entry((truncate_to_period(internal_ts, period), point_idx))
+            .or_insert((0.0, f64::INFINITY, f64::NEG_INFINITY, 0));
+        if !value.is_nan() {
+            bucket.0 += value;
+            bucket.1 = bucket.1.min(value);
+            bucket.2 = bucket.2.max(value);
+            bucket.3 += 1;
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|((internal_ts, point_idx), (sum, min, max, count))| {
+            let value = match (count, agg) {
+                (0, _) => f64::NAN,
+                (_, Agg::Mean) => sum / count as f64,
+                (_, Agg::Max) => max,
+                (_, Agg::Min) => min,
+                (_, Agg::Sum) => sum,
+            };
+            (internal_ts, point_idx, value)
+        })
+        .collect()
+}
+
+/// A CF time `units` attribute such as `"days since 2000-01-01 00:00:00"`.
+pub struct TimeUnits {
+    seconds_per_unit: f64,
+    reference: NaiveDateTime,
+}
+
+impl TimeUnits {
+    /// The layout assumed when a file carries no `units`: hours since 1900-01-01.
+    pub fn default_hours_since_1900() -> Self {
+        TimeUnits {
+            seconds_per_unit: 3600.0,
+            reference: BASE_DATETIME_NAIVE,
+        }
+    }
+
+    /// Parses `<seconds|hours|days> since <date>[ <time>]`.
+    pub fn parse(units: &str) -> Result<Self, Box<dyn Error>> {
+        let (unit, reference) = units
+            .split_once(" since ")
+            .ok_or_else(|| format!("Unrecognized time units '{}'", units))?;
+
+        let seconds_per_unit = match unit.trim().to_lowercase().as_str() {
+            "seconds" | "second" | "secs" | "sec" | "s" => 1.0,
+            "hours" | "hour" | "hrs" | "hr" | "h" => 3600.0,
+            "days" | "day" | "d" => 86400.0,
+            other => return Err(format!("Unsupported time unit '{}' in '{}'", other, units).into()),
+        };
+
+        let reference = reference
+            .trim()
+            .trim_end_matches('Z')
+            .trim_end_matches(" UTC");
+        let reference = [
+            "%Y-%m-%d %H:%M:%S%.f",
+            "%Y-%m-%dT%H:%M:%S%.f",
+            "%Y-%m-%d %H:%M",
+        ]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(reference, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(reference, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| format!("Unrecognized reference date in time units '{}'", units))?;
+
+        Ok(TimeUnits {
+            seconds_per_unit,
+            reference,
+        })
+    }
+
+    /// Converts a raw time value to the internal seconds-since-1900 representation.
+    pub fn to_internal_seconds(&self, raw: f64) -> i64 {
+        let offset = (self.reference - BASE_DATETIME_NAIVE).num_seconds();
+        offset + (raw * self.seconds_per_unit).round() as i64
+    }
+}
+
 pub fn collect_input_files(
     input_path: &PathBuf,
 ) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
@@ -77,6 +196,254 @@ pub fn extract_locations(dataset: &netcdf::File) -> Result<(Vec<f32>, Vec<f32>),
     Ok((lat_seq, lon_seq))
 }
 
+/// Positions of the time, latitude and longitude axes within a variable's dimensions.
+pub struct DimLayout {
+    pub time: usize,
+    pub lat: usize,
+    pub lon: usize,
+    /// Axis and selected index of the vertical dimension of a 4-D variable.
+    pub level: Option<(usize, usize)>,
+    ndims: usize,
+}
+
+const LEVEL_NAMES: [&str; 8] = [
+    "level", "lev", "plev", "pressure", "isobaric", "height", "depth", "z",
+];
+
+impl DimLayout {
+    /// Locates the axes by name, so `(time, lat, lon)` and `(lat, lon, time)` both work.
+    ///
+    /// The time axis must be named `time` or `t`. When lat/lon (or the level of a
+    /// 4-D variable) aren't named as expected, the remaining axes are taken in
+    /// order as level, lat, lon. A 4-D variable requires `level` to be given.
+    pub fn locate(
+        dims: &[netcdf::Dimension],
+        level: Option<usize>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let names: Vec<String> = dims.iter().map(|d| d.name().to_lowercase()).collect();
+
+        let time_candidates: Vec<usize> = names
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.as_str() == "time" || n.as_str() == "t")
+            .map(|(i, _)| i)
+            .collect();
+        let time = match time_candidates.as_slice() {
+            [idx] => *idx,
+            [] => {
+                return Err(format!(
+                    "No time dimension (named 'time' or 't') found among dimensions {:?}",
+                    names
+                )
+                .into())
+            }
+            _ => {
+                return Err(format!(
+                    "Ambiguous time dimension: several of {:?} are named 'time' or 't'",
+                    names
+                )
+                .into())
+            }
+        };
+
+        let has_level = match names.len() {
+            3 => false,
+            4 => true,
+            _ => {
+                return Err(format!(
+                    "Unsupported dimensions {:?} (expected time, lat, lon and optionally a level)",
+                    names
+                )
+                .into())
+            }
+        };
+
+        let find =
+            |candidates: &[&str]| names.iter().position(|n| candidates.contains(&n.as_str()));
+        let mut lat = find(&["lat", "latitude"]);
+        let mut lon = find(&["lon", "longitude"]);
+        let mut level_axis = if has_level { find(&LEVEL_NAMES) } else { None };
+
+        let taken = [Some(time), lat, lon, level_axis];
+        let mut unassigned = (0..names.len())
+            .filter(|i| !taken.contains(&Some(*i)))
+            .collect::<Vec<_>>()
+            .into_iter();
+        if has_level && level_axis.is_none() {
+            level_axis = unassigned.next();
+        }
+        if lat.is_none() {
+            lat = unassigned.next();
+        }
+        if lon.is_none() {
+            lon = unassigned.next();
+        }
+
+        let (Some(lat), Some(lon)) = (lat, lon) else {
+            return Err(format!("Could not tell time/lat/lon apart in {:?}", names).into());
+        };
+        let mut axes = vec![time, lat, lon];
+        axes.extend(level_axis);
+        axes.sort_unstable();
+        axes.dedup();
+        if axes.len() != names.len() {
+            return Err(format!("Could not tell time/lat/lon apart in {:?}", names).into());
+        }
+
+        let level = match (level_axis, level) {
+            (Some(axis), Some(idx)) => {
+                let len = dims[axis].len();
+                if idx >= len {
+                    return Err(format!(
+                        "Level index {} out of bounds for dimension '{}' (length {})",
+                        idx, names[axis], len
+                    )
+                    .into());
+                }
+                Some((axis, idx))
+            }
+            (Some(axis), None) => {
+                return Err(format!(
+                    "Variable has a vertical dimension '{}' (length {}); select one with --level <index>",
+                    names[axis],
+                    dims[axis].len()
+                )
+                .into())
+            }
+            (None, _) => None,
+        };
+
+        Ok(DimLayout {
+            time,
+            lat,
+            lon,
+            level,
+            ndims: names.len(),
+        })
+    }
+
+    /// Builds the slice selecting the whole time axis at one grid point (and level).
+    pub fn point_extents(&self, lat_idx: usize, lon_idx: usize) -> Vec<netcdf::Extent> {
+        let mut extents: Vec<netcdf::Extent> = vec![0.into(); self.ndims];
+        extents[self.time] = (..).into();
+        extents[self.lat] = lat_idx.into();
+        extents[self.lon] = lon_idx.into();
+        if let Some((axis, idx)) = self.level {
+            extents[axis] = idx.into();
+        }
+        extents
+    }
+}
+
+/// CF packing attributes of a variable: `scale_factor`, `add_offset` and fill markers.
+pub struct Packing {
+    scale_factor: f64,
+    add_offset: f64,
+    fill_values: Vec<f64>,
+}
+
+impl Packing {
+    /// Reads the packing attributes, defaulting to the identity when they're absent.
+    pub fn from_variable(var: &netcdf::Variable) -> Result<Self, Box<dyn Error>> {
+        let scale_factor = attribute_f64(var, "scale_factor")?.unwrap_or(1.0);
+        let add_offset = attribute_f64(var, "add_offset")?.unwrap_or(0.0);
+        let fill_values = ["_FillValue", "missing_value"]
+            .iter()
+            .map(|name| attribute_f64(var, name))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        Ok(Packing {
+            scale_factor,
+            add_offset,
+            fill_values,
+        })
+    }
+
+    /// Maps a raw stored value to its physical value, or NaN if it marks missing data.
+    pub fn unpack(&self, raw: f64) -> f64 {
+        if self.fill_values.contains(&raw) {
+            f64::NAN
+        } else {
+            raw * self.scale_factor + self.add_offset
+        }
+    }
+}
+
+fn attribute_f64(var: &netcdf::Variable, name: &str) -> Result<Option<f64>, Box<dyn Error>> {
+    match var.attribute_value(name) {
+        None => Ok(None),
+        Some(value) => {
+            let value = f64::try_from(value?).map_err(|e| {
+                format!(
+                    "Attribute '{}' of '{}' is not a number: {}",
+                    name,
+                    var.name(),
+                    e
+                )
+            })?;
+            Ok(Some(value))
+        }
+    }
+}
+
+/// A named location from a points file.
+pub struct PointSpec {
+    pub name: String,
+    pub lat: f32,
+    pub lon: f32,
+}
+
+/// Reads a `name,lat,lon` CSV of points to extract, in file order.
+pub fn read_points(path: &Path) -> Result<Vec<PointSpec>, Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_path(path)
+        .map_err(|e| format!("Failed to open points file '{}': {}", path.display(), e))?;
+
+    let headers = rdr.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Points file '{}' has no '{}' column", path.display(), name))
+    };
+    let (name_col, lat_col, lon_col) = (column("name")?, column("lat")?, column("lon")?);
+
+    let mut points = Vec::new();
+    let mut seen = HashSet::new();
+    for (row, record) in rdr.records().enumerate() {
+        let record = record?;
+        let field = |col: usize| record.get(col).unwrap_or("").trim();
+        let parse = |col: usize| {
+            field(col).parse::<f32>().map_err(|e| {
+                format!(
+                    "Invalid coordinate '{}' on row {} of '{}': {}",
+                    field(col),
+                    row + 1,
+                    path.display(),
+                    e
+                )
+            })
+        };
+
+        let name = field(name_col).to_string();
+        if !seen.insert(name.clone()) {
+            return Err(format!("Duplicate point name '{}' in '{}'", name, path.display()).into());
+        }
+        points.push(PointSpec {
+            name,
+            lat: parse(lat_col)?,
+            lon: parse(lon_col)?,
+        });
+    }
+
+    if points.is_empty() {
+        return Err(format!("No points found in '{}'", path.display()).into());
+    }
+    Ok(points)
+}
+
 pub fn write_data_to_csv(
     output_path: &PathBuf,
     variable_name: &str,
@@ -97,7 +464,7 @@ pub fn write_data_to_csv(
     wtr.write_record(&["timestamp", variable_name])?;
 
     data.iter().try_for_each(|(internal_ts, value)| {
-        let timestamp_str = hours_to_timestamp_string(*internal_ts);
+        let timestamp_str = seconds_to_timestamp_string(*internal_ts);
         wtr.write_record(&[timestamp_str, format!("{:.2}", value)])
     })?;
 
@@ -105,3 +472,38 @@ pub fn write_data_to_csv(
     println!("Data successfully written to {}", output_path.display());
     Ok(())
 }
+
+/// Writes `(internal_ts, point_idx, value)` rows as `timestamp,point_name,value`.
+pub fn write_long_data_to_csv(
+    output_path: &PathBuf,
+    point_names: &[&str],
+    data: &mut [(i64, usize, f64)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if data.is_empty() {
+        return Err(
+            "No data extracted. All files/groups might have failed processing or contained no matching data."
+                .into(),
+        );
+    }
+
+    data.par_sort_unstable_by_key(|k| (k.0, k.1));
+
+    let file = File::create(output_path)?;
+    let mut wtr = Writer::from_writer(file);
+
+    wtr.write_record(["timestamp", "point_name", "value"])?;
+
+    data.iter()
+        .try_for_each(|(internal_ts, point_idx, value)| {
+            let timestamp_str = seconds_to_timestamp_string(*internal_ts);
+            wtr.write_record([
+                timestamp_str,
+                point_names[*point_idx].to_string(),
+                format!("{:.2}", value),
+            ])
+        })?;
+
+    wtr.flush()?;
+    println!("Data successfully written to {}", output_path.display());
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use clap::{Parser, Subcommand};
+use dialoguer::Confirm;
 use indicatif::{ParallelProgressIterator, ProgressFinish, ProgressStyle};
 use rayon::prelude::*;
 use std::borrow::Cow;
@@ -6,6 +7,7 @@ use std::fs;
 use std::fs::read_dir;
 use std::io;
 use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
 
 /// A simple tool to merge image and video files into a single directory.
 #[derive(Parser)]
@@ -13,8 +15,18 @@ use std::path::{Path, PathBuf};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Comma-separated list of extensions treated as media, replacing the built-in list.
+    /// Files with other extensions are still checked by their content.
+    #[arg(short, long, global = true, value_delimiter = ',')]
+    extensions: Option<Vec<String>>,
 }
 
+/// Extensions recognized as images or videos without looking at the file content.
+const DEFAULT_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "bmp", "tif", "tiff", "heic", "heif", "avif", "jxl",
+    "mp4", "avi", "mov", "mkv", "webm", "m4v", "flv", "wmv", "mpg", "mpeg", "3gp",
+];
+
 #[derive(Subcommand)]
 enum Commands {
     /// Audit subdirectories within INPUT_DIR for those with fewer than MIN_COUNT images.
@@ -22,6 +34,12 @@ enum Commands {
         /// Minimum number of images required in a subdirectory.
         #[arg(short, long, default_value = "5")]
         min_count: u32,
+        /// How many levels of subdirectories to audit.
+        #[arg(short, long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+        depth: u32,
+        /// Write every audited subdirectory and its media count to this CSV file.
+        #[arg(long)]
+        csv: Option<PathBuf>,
         /// Input directory to audit.
         input_dir: PathBuf,
     },
@@ -30,6 +48,12 @@ enum Commands {
         /// Output directory for the eradicated files.
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Only list the files that would be deleted or moved.
+        #[arg(short, long)]
+        dry_run: bool,
+        /// Delete without asking for confirmation when no output directory is given.
+        #[arg(short, long)]
+        yes: bool,
         /// Input directories to process.
         input_dirs: Vec<PathBuf>,
     },
@@ -41,6 +65,10 @@ enum Commands {
         /// Enable verbose output.
         #[arg(short, long)]
         verbose: bool,
+        /// Mirror each file's path relative to its input directory under OUTPUT instead of
+        /// grouping files into artist folders.
+        #[arg(short, long)]
+        preserve_tree: bool,
         /// Output directory for merged files.
         #[arg(short, long, default_value = "./merged")]
         output: PathBuf,
@@ -64,52 +92,96 @@ fn glob_dir(p: &PathBuf) -> Vec<PathBuf> {
 
 fn main() -> io::Result<()> {
     let cli = Cli::parse();
+    let extensions: Vec<String> = match &cli.extensions {
+        Some(exts) => exts.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect(),
+        None => DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+    };
 
     match &cli.command {
-        Commands::Audit { min_count, input_dir } => audit(input_dir, *min_count),
-        Commands::Eradicate { output, input_dirs } => {
-            eradicate(output, input_dirs.iter().flat_map(glob_dir).collect::<Vec<PathBuf>>().as_slice())
+        Commands::Audit { min_count, depth, csv, input_dir } => {
+            audit(input_dir, *min_count, *depth, csv.as_deref(), &extensions)
+        }
+        Commands::Eradicate { output, dry_run, yes, input_dirs } => {
+            eradicate(output, *dry_run, *yes, input_dirs.iter().flat_map(glob_dir).collect::<Vec<PathBuf>>().as_slice(), &extensions)
         }
-        Commands::Merge { cut, verbose, output, input_dirs } => {
-            merge(*cut, *verbose, output, input_dirs
-                .iter().flat_map(glob_dir).collect::<Vec<PathBuf>>().as_slice())
+        Commands::Merge { cut, verbose, preserve_tree, output, input_dirs } => {
+            merge(*cut, *verbose, *preserve_tree, output, input_dirs
+                .iter().flat_map(glob_dir).collect::<Vec<PathBuf>>().as_slice(), &extensions)
         }
     }
 }
 
 /// Check subdirectories within INPUT_DIR for those with fewer than MIN_COUNT images.
-fn audit(input_dir: &Path, min_count: u32) -> io::Result<()> {
+fn audit(input_dir: &Path, min_count: u32, depth: u32, csv_path: Option<&Path>, extensions: &[String]) -> io::Result<()> {
     if !input_dir.is_dir() {
         return Err(io::Error::new(io::ErrorKind::Other, format!("Input directory {:?} does not exist or is not a directory.", input_dir)));
     }
 
-    read_dir(input_dir)
-        .expect("Failed to read directory")
-        .par_bridge()
+    let mut counts: Vec<(PathBuf, usize)> = WalkDir::new(input_dir)
+        .min_depth(1)
+        .max_depth(depth as usize)
+        .into_iter()
         .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.path().is_dir())
-        .for_each(|entry| {
-            let path = entry.path();
+        .filter(|entry| entry.file_type().is_dir())
+        .par_bridge()
+        .map(|entry| {
+            let path = entry.into_path();
             let file_count = fs::read_dir(&path)
                 .expect("Failed to read directory")
                 .filter_map(|e| e.ok())
-                .filter(|e| is_image_or_video(&e.path()))
+                .filter(|e| is_image_or_video(&e.path(), extensions))
                 .count();
             if file_count < min_count as usize {
                 println!("{:?} has fewer than {} images/video files (found {}).", path, min_count, file_count);
             }
-        });
+            (path, file_count)
+        })
+        .collect();
+
+    if let Some(csv_path) = csv_path {
+        counts.sort();
+        let mut wtr = csv::Writer::from_path(csv_path)?;
+        wtr.write_record(["directory", "media_count"])?;
+        for (path, count) in &counts {
+            wtr.write_record([path.to_string_lossy().as_ref(), count.to_string().as_str()])?;
+        }
+        wtr.flush()?;
+        println!("📄 Wrote {} directories to {:?}", counts.len(), csv_path);
+    }
 
     Ok(())
 }
 
 /// Delete all non-image files in the target directories.
-fn eradicate(output: &Option<PathBuf>, input_dirs: &[PathBuf]) -> io::Result<()> {
-    get_multimedia(input_dirs)
+fn eradicate(output: &Option<PathBuf>, dry_run: bool, yes: bool, input_dirs: &[PathBuf], extensions: &[String]) -> io::Result<()> {
+    let files: Vec<PathBuf> = list_files(input_dirs)
+        .into_iter()
+        .filter(|path| !is_image_or_video(path, extensions))
+        .collect();
+    println!("💡 Found {} non-media files", files.len());
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    if !dry_run && output.is_none() && !yes && !Confirm::new()
+        .with_prompt(format!("Permanently delete {} files?", files.len()))
+        .default(false)
+        .interact()
+        .map_err(io::Error::other)? {
+        println!("Aborted, nothing was deleted.");
+        return Ok(());
+    }
+
+    files
         .iter()
         .par_bridge()
         .try_for_each(|path| {
-            if let Some(output_dir) = output {
+            if dry_run {
+                match output {
+                    Some(output_dir) => println!("Would move {:?} -> {:?}", path, output_dir.join(path.file_name().unwrap())),
+                    None => println!("Would delete {:?}", path),
+                }
+            } else if let Some(output_dir) = output {
                 // Create the output directory if it doesn't exist
                 fs::create_dir_all(output_dir)?;
                 // Move the file to the output directory
@@ -128,11 +200,15 @@ fn eradicate(output: &Option<PathBuf>, input_dirs: &[PathBuf]) -> io::Result<()>
     Ok(())
 }
 
-fn merge(cut: bool, verbose: bool, output: &PathBuf, input_dirs: &[PathBuf]) -> io::Result<()> {
+fn merge(cut: bool, verbose: bool, preserve_tree: bool, output: &PathBuf, input_dirs: &[PathBuf], extensions: &[String]) -> io::Result<()> {
     fs::create_dir_all(output)?;
     println!("Merging merge {:?} into {:?}", input_dirs, output);
 
-    let files = get_multimedia(input_dirs);
+    // Mirroring a tree needs every file below the input directories, not only one level deep
+    let files: Vec<PathBuf> = if preserve_tree { list_files_recursively(input_dirs, output) } else { list_files(input_dirs) }
+        .into_iter()
+        .filter(|path| is_image_or_video(path, extensions))
+        .collect();
     println!("💡 Found {} files", files.len());
     files
         .iter()
@@ -140,31 +216,14 @@ fn merge(cut: bool, verbose: bool, output: &PathBuf, input_dirs: &[PathBuf]) ->
         .progress_count(files.len() as u64)
         .with_finish(ProgressFinish::WithMessage(Cow::from("Done")))
         .with_style(ProgressStyle::with_template("{spinner:.green} [{elapsed}/{duration}] [{bar:40.green/blue}] {msg} {pos}/{len} ({per_sec})").unwrap())
-        .map(|path| (path.clone(), path
-            .parent()
-            .expect("Failed to get parent directory")
-            .file_name()
-            .expect("Failed to get file name")
-            .to_string_lossy()
-            .split('_')
-            .last()
-            .expect("Failed to get artist ID")
-            .to_owned()
-        ))
-        .map(|(p, artist_id)| (p.clone(), read_dir(output)
-            .expect("Failed to read directory")
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.file_name().to_string_lossy().contains(artist_id.as_str()))
-            .last()
-            .map(|entry| entry.path())
-            .unwrap_or(output.join(
-                p
-                    .parent()
-                    .expect("Failed to get parent directory")
-                    .file_name()
-                    .expect("Failed to get file name"))
-            ))
-        )
+        .map(|path| {
+            let out_dir = if preserve_tree {
+                tree_destination(path, input_dirs, output)
+            } else {
+                artist_destination(path, output)
+            };
+            (path.clone(), out_dir)
+        })
         .filter(|(src, out)| should_process_file(src, &out, verbose))
         .try_for_each(|(path, out_dir)| {
             let new_path = out_dir.join(path.file_name().unwrap());
@@ -184,6 +243,45 @@ fn merge(cut: bool, verbose: bool, output: &PathBuf, input_dirs: &[PathBuf]) ->
         })
 }
 
+/// Pick the output folder whose name contains the artist ID of the file's parent folder,
+/// or a new folder named after the parent when none exists yet.
+fn artist_destination(path: &Path, output: &Path) -> PathBuf {
+    let folder_name = path
+        .parent()
+        .and_then(|parent| parent.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let key = artist_id(&folder_name).unwrap_or_else(|| folder_name.clone());
+    read_dir(output)
+        .expect("Failed to read directory")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().contains(key.as_str()))
+        .last()
+        .map(|entry| entry.path())
+        .unwrap_or_else(|| output.join(folder_name))
+}
+
+/// Mirror the file's parent directory, relative to the closest input directory, under `output`.
+fn tree_destination(path: &Path, input_dirs: &[PathBuf], output: &Path) -> PathBuf {
+    let parent = path.parent().expect("Failed to get parent directory");
+    input_dirs
+        .iter()
+        .filter_map(|dir| parent.strip_prefix(dir).ok())
+        .min_by_key(|rel| rel.components().count())
+        .map(|rel| output.join(rel))
+        .unwrap_or_else(|| output.to_path_buf())
+}
+
+/// Extract the artist ID from a folder named like `name_123`, i.e. the part after the last
+/// underscore. Returns `None` when the name has no underscore or nothing follows it.
+fn artist_id(folder_name: &str) -> Option<String> {
+    folder_name
+        .rsplit_once('_')
+        .map(|(_, id)| id)
+        .filter(|id| !id.is_empty())
+        .map(str::to_owned)
+}
+
 /// Function that determines whether a file should be processed based on size comparison.
 fn should_process_file(source: &Path, output: &PathBuf, verbose: bool) -> bool {
     let source_metadata = match fs::metadata(source) {
@@ -211,11 +309,28 @@ fn should_process_file(source: &Path, output: &PathBuf, verbose: bool) -> bool {
 }
 
 /// Check if a file is an image or video.
-fn is_image_or_video(path: &PathBuf) -> bool {
-    matches!(path.extension().and_then(|s| s.to_str()), Some("jpg" | "jpeg" | "png" | "gif" | "mp4" | "avi" | "mov"))
+///
+/// The extension is matched case-insensitively against `extensions`; when it isn't
+/// recognized (or missing), the file's magic bytes decide.
+fn is_image_or_video(path: &Path, extensions: &[String]) -> bool {
+    let known = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        .unwrap_or(false);
+    known || (path.is_file() && is_media_content(path))
 }
 
-fn get_multimedia(dir_path: &[PathBuf]) -> Vec<PathBuf> {
+/// Sniff the file header and report whether it looks like an image or video.
+fn is_media_content(path: &Path) -> bool {
+    matches!(
+        infer::get_from_path(path).ok().flatten().map(|t| t.matcher_type()),
+        Some(infer::MatcherType::Image | infer::MatcherType::Video)
+    )
+}
+
+/// List the files inside the subdirectories of each directory in `dir_path`.
+fn list_files(dir_path: &[PathBuf]) -> Vec<PathBuf> {
     dir_path
         .iter()
         .par_bridge()
@@ -228,7 +343,56 @@ fn get_multimedia(dir_path: &[PathBuf]) -> Vec<PathBuf> {
         .filter_map(|dir| dir.ok())
         .flatten_iter()
         .filter_map(|entry| entry.ok())
-        .filter(|entry| is_image_or_video(&entry.path()))
         .map(|entry| entry.path())
+        .filter(|path| path.is_file())
         .collect()
-}
\ No newline at end of file
+}
+
+/// List every file below each directory in `dir_path`, at any depth, skipping `output`
+/// in case it sits inside one of them.
+fn list_files_recursively(dir_path: &[PathBuf], output: &Path) -> Vec<PathBuf> {
+    let output = output.canonicalize().unwrap_or_else(|_| output.to_path_buf());
+    dir_path
+        .iter()
+        .filter(|dir| dir.is_dir())
+        .flat_map(|dir| {
+            WalkDir::new(dir)
+                .into_iter()
+                .filter_entry(|entry| entry.path().canonicalize().map_or(true, |p| p != output))
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artist_id_extraction() {
+        assert_eq!(artist_id("art"), None);
+        assert_eq!(artist_id("name_123"), Some("123".to_string()));
+        assert_eq!(artist_id("a_b_c"), Some("c".to_string()));
+        assert_eq!(artist_id("trailing_"), None);
+    }
+
+    #[test]
+    fn test_preserve_tree_merges_nested_files() {
+        let root = std::env::temp_dir().join(format!("pam_tree_{}", std::process::id()));
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(input.join("a").join("b")).unwrap();
+        fs::write(input.join("a").join("b").join("x.jpg"), b"x").unwrap();
+        fs::write(input.join("top.jpg"), b"top").unwrap();
+
+        let extensions = vec!["jpg".to_string()];
+        merge(false, false, true, &output, std::slice::from_ref(&input), &extensions).unwrap();
+
+        assert!(output.join("a").join("b").join("x.jpg").is_file());
+        assert!(output.join("top.jpg").is_file());
+        fs::remove_dir_all(root).ok();
+    }
+}
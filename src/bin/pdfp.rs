@@ -1,5 +1,9 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use rayon::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::{fs, io};
@@ -28,19 +32,123 @@ enum Commands {
         /// Minimum file size (in kilobytes) to keep; files smaller than this will be deleted
         #[arg(long, short)]
         min_size: Option<u64>,
+
+        /// Extraction engine: the `pdfimages` command or the built-in PDF parser
+        #[arg(long, short, value_enum, default_value_t = Engine::External)]
+        engine: Engine,
+
+        /// Drop images whose content is identical to one already extracted
+        #[arg(long, short)]
+        dedup: bool,
+
+        /// Only extract images from these pages, e.g. `1-5,10,20-`
+        #[arg(long, short, value_parser = parse_page_ranges)]
+        pages: Option<PageRanges>,
     },
 }
 
+/// A set of 1-based page numbers, stored as sorted, non-overlapping inclusive ranges.
+///
+/// An end of `None` means the range runs to the last page.
+#[derive(Clone, Debug, PartialEq)]
+struct PageRanges(Vec<(u32, Option<u32>)>);
+
+impl PageRanges {
+    /// Returns `true` if `page` falls into one of the ranges.
+    fn contains(&self, page: u32) -> bool {
+        self.0.iter().any(|&(first, last)| page >= first && last.is_none_or(|last| page <= last))
+    }
+}
+
+/// Parses a page spec such as `1-5,10,20-` into merged page ranges.
+fn parse_page_ranges(spec: &str) -> Result<PageRanges, String> {
+    let parse_page = |s: &str| -> Result<u32, String> {
+        match s.trim().parse::<u32>() {
+            Ok(0) | Err(_) => Err(format!("Invalid page number '{}'", s.trim())),
+            Ok(page) => Ok(page),
+        }
+    };
+
+    let mut ranges = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let range = match part.split_once('-') {
+            None => {
+                let page = parse_page(part)?;
+                (page, Some(page))
+            }
+            Some((first, last)) if last.trim().is_empty() => (parse_page(first)?, None),
+            Some((first, last)) => {
+                let (first, last) = (parse_page(first)?, parse_page(last)?);
+                if first > last {
+                    return Err(format!("Inverted page range '{}'", part));
+                }
+                (first, Some(last))
+            }
+        };
+        ranges.push(range);
+    }
+    if ranges.is_empty() {
+        return Err("Page spec is empty".to_string());
+    }
+
+    // Merge overlapping or adjacent ranges so each page is extracted once
+    ranges.sort();
+    let mut merged: Vec<(u32, Option<u32>)> = Vec::new();
+    for (first, last) in ranges {
+        match merged.last_mut() {
+            Some((_, prev_last)) if prev_last.is_none_or(|prev| first <= prev + 1) => {
+                *prev_last = match (*prev_last, last) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    _ => None,
+                };
+            }
+            _ => merged.push((first, last)),
+        }
+    }
+
+    Ok(PageRanges(merged))
+}
+
+/// The outcome of extracting images from one PDF.
+#[derive(Debug, Serialize)]
+struct PdfReport {
+    pdf: String,
+    images: usize,
+    succeeded: bool,
+    error: String,
+}
+
+/// How images are pulled out of a PDF.
+#[derive(Clone, Copy, ValueEnum)]
+enum Engine {
+    /// Shell out to `pdfimages` from poppler-utils
+    External,
+    /// Decode the image XObjects directly, no external tools required
+    Native,
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Extract { input_dir, out_dir, min_size } => {
+        Commands::Extract { input_dir, out_dir, min_size, engine, dedup, pages } => {
             // Ensure output directory exists
             fs::create_dir_all(out_dir).expect("Failed to create output directory");
 
             // Process each PDF file in the input directory
-            process_pdfs(input_dir, out_dir).expect("Failed to process PDFs");
+            let reports = process_pdfs(input_dir, out_dir, *engine, pages.as_ref())
+                .expect("Failed to process PDFs");
+
+            // Summarize the extraction and keep a copy next to the images
+            print_summary(&reports);
+            write_report(&out_dir.join("report.csv"), &reports).expect("Failed to write report");
+
+            // Remove images already extracted from another page or PDF
+            if *dedup {
+                let dropped =
+                    remove_duplicate_images(out_dir).expect("Failed to remove duplicates");
+                println!("Dropped {} duplicate images", dropped);
+            }
 
             // Remove files smaller than the specified minimum size
             if let Some(min_size_value) = min_size {
@@ -54,19 +162,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 /// Processes each PDF file in the input directory by extracting images.
 ///
+/// A PDF that fails to extract does not stop the others; its error is recorded in its report.
+///
 /// # Arguments
 ///
 /// * `input_dir` - A reference to the path of the input directory containing PDF files.
 /// * `out_dir` - A reference to the path of the output directory for extracted images.
+/// * `engine` - The engine used to extract the images.
+/// * `pages` - The pages to extract images from, or `None` for all pages.
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn std::error::Error>>` - An empty result on success, or an error on failure.
+/// * `Result<Vec<PdfReport>, Box<dyn std::error::Error>>` - One report per PDF sorted by name, or
+///   an error if the input directory cannot be read.
 fn process_pdfs(
     input_dir: &PathBuf,
     out_dir: &Path,
-) -> Result<(), Box<dyn std::error::Error>> {
-    fs::read_dir(input_dir)?
+    engine: Engine,
+    pages: Option<&PageRanges>,
+) -> Result<Vec<PdfReport>, Box<dyn std::error::Error>> {
+    let mut reports: Vec<PdfReport> = fs::read_dir(input_dir)?
         .par_bridge()
         .filter_map(Result::ok)
         .filter(|entry| entry.path().is_file() &&
@@ -74,7 +189,7 @@ fn process_pdfs(
                 .extension()
                 .is_some_and(|s| s.to_ascii_lowercase().to_str() == Some("pdf")))
         .inspect(|entry| println!("Processing {}", entry.path().display()))
-        .for_each(|entry| {
+        .map(|entry| {
             let path = entry.path();
             let pdf_name = path.file_stem().unwrap().to_str().unwrap();
             let pdf_output_dir = out_dir.join(pdf_name);
@@ -82,35 +197,322 @@ fn process_pdfs(
             // Create subdirectory for this PDF's images
             fs::create_dir_all(&pdf_output_dir).expect("Failed to create output directory");
 
-            // Step 1: Extract images from PDF using pdfimages
-            extract_images_from_pdf(&path, &pdf_output_dir).expect("Failed to execute pdfimages");
-        });
+            // Step 1: Extract images from PDF with the selected engine
+            let result = match engine {
+                Engine::External => extract_images_from_pdf(&path, &pdf_output_dir, pages)
+                    .map_err(|e| e.to_string())
+                    .map(|_| count_files(&pdf_output_dir)),
+                Engine::Native => extract_images_native(&path, &pdf_output_dir, pages)
+                    .map_err(|e| e.to_string()),
+            };
+            if let Err(e) = &result {
+                eprintln!("Failed to extract images from {}: {}", path.display(), e);
+            }
+
+            PdfReport {
+                pdf: path.file_name().unwrap().to_string_lossy().into_owned(),
+                images: *result.as_ref().unwrap_or(&0),
+                succeeded: result.is_ok(),
+                error: result.err().unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    reports.sort_by(|a, b| a.pdf.cmp(&b.pdf));
+    Ok(reports)
+}
+
+/// Counts the files directly inside `dir`.
+fn count_files(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok).filter(|e| e.path().is_file()).count())
+        .unwrap_or(0)
+}
+
+/// Prints a table of images extracted per PDF, followed by the PDFs that failed.
+fn print_summary(reports: &[PdfReport]) {
+    let width = reports.iter().map(|r| r.pdf.chars().count()).max().unwrap_or(0).max(3);
+
+    println!("\n{:<width$}  {:>6}  Status", "PDF", "Images", width = width);
+    for report in reports {
+        let status = if report.succeeded { "ok" } else { "failed" };
+        println!("{:<width$}  {:>6}  {}", report.pdf, report.images, status, width = width);
+    }
+
+    let total: usize = reports.iter().map(|r| r.images).sum();
+    let failed: Vec<&PdfReport> = reports.iter().filter(|r| !r.succeeded).collect();
+    println!(
+        "\n{} PDFs processed, {} images extracted, {} failed",
+        reports.len(),
+        total,
+        failed.len()
+    );
+    for report in failed {
+        println!("  ✗ {}: {}", report.pdf, report.error);
+    }
+}
 
+/// Writes the per-PDF reports as CSV.
+///
+/// # Arguments
+///
+/// * `path` - A reference to the path of the CSV file to write.
+/// * `reports` - The reports to write, one row each.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - An empty result on success, or an error on failure.
+fn write_report(path: &Path, reports: &[PdfReport]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for report in reports {
+        writer.serialize(report)?;
+    }
+    writer.flush()?;
     Ok(())
 }
 
 /// Extracts images from a PDF file using the `pdfimages` command.
 ///
+/// When pages are given, `pdfimages` is run once per range with its `-f`/`-l` page arguments.
+/// A non-zero exit status (e.g. an encrypted or corrupt PDF) is reported as an error.
+///
 /// # Arguments
 ///
 /// * `pdf_path` - A reference to the path of the PDF file.
 /// * `output_dir` - A reference to the path of the output directory for extracted images.
+/// * `pages` - The pages to extract images from, or `None` for all pages.
 ///
 /// # Returns
 ///
 /// * `Result<(), io::Error>` - An empty result on success, or an I/O error on failure.
-fn extract_images_from_pdf(pdf_path: &PathBuf, output_dir: &PathBuf) -> io::Result<()> {
-    Command::new("pdfimages")
-        .arg("-png") // Extract as PNG
-        .arg("-p") // Preserve aspect ratio
-        .arg(pdf_path)
-        .arg(format!("{}/", output_dir.display()))
-        .output()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to execute pdfimages: {}", e)))?;
+fn extract_images_from_pdf(
+    pdf_path: &PathBuf,
+    output_dir: &PathBuf,
+    pages: Option<&PageRanges>,
+) -> io::Result<()> {
+    let ranges = match pages {
+        Some(pages) => pages.0.clone(),
+        None => vec![(1, None)],
+    };
+
+    for (first, last) in ranges {
+        let mut command = Command::new("pdfimages");
+        command
+            .arg("-png") // Extract as PNG
+            .arg("-p") // Preserve aspect ratio
+            .arg("-f")
+            .arg(first.to_string());
+        if let Some(last) = last {
+            command.arg("-l").arg(last.to_string());
+        }
+        let output = command
+            .arg(pdf_path)
+            .arg(format!("{}/", output_dir.display()))
+            .output()
+            .map_err(|e| {
+                io::Error::new(io::ErrorKind::Other, format!("Failed to execute pdfimages: {}", e))
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "pdfimages exited with {}: {}",
+                output.status,
+                stderr.trim()
+            )));
+        }
+    }
 
     Ok(())
 }
 
+/// Extracts images from a PDF file by decoding its image XObjects.
+///
+/// `DCTDecode` and `JPXDecode` streams are written as-is (`.jpg` / `.jp2`); `FlateDecode` and
+/// unfiltered streams are decoded to pixels and written as `.png`. Other encodings are skipped.
+///
+/// # Arguments
+///
+/// * `pdf_path` - A reference to the path of the PDF file.
+/// * `output_dir` - A reference to the path of the output directory for extracted images.
+/// * `pages` - The pages to extract images from, or `None` for all pages.
+///
+/// # Returns
+///
+/// * `Result<usize, Box<dyn std::error::Error>>` - The number of images written, or an error.
+fn extract_images_native(
+    pdf_path: &Path,
+    output_dir: &Path,
+    pages: Option<&PageRanges>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let doc = lopdf::Document::load(pdf_path)?;
+    let mut written = 0;
+
+    for (page_number, page_id) in doc.get_pages() {
+        if pages.is_some_and(|pages| !pages.contains(page_number)) {
+            continue;
+        }
+
+        // Pages without an XObject resource dictionary have no images
+        let Ok(images) = doc.get_page_images(page_id) else {
+            continue;
+        };
+
+        for (index, image) in images.iter().enumerate() {
+            let stem = output_dir.join(format!("{:03}-{:03}", page_number, index));
+            match write_native_image(&doc, image, &stem) {
+                Ok(true) => written += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("Skipping image {} on page {}: {}", index, page_number, e),
+            }
+        }
+    }
+
+    Ok(written)
+}
+
+/// Writes one image XObject next to `stem`, returning `false` when its encoding is unsupported.
+fn write_native_image(
+    doc: &lopdf::Document,
+    image: &lopdf::xobject::PdfImage,
+    stem: &Path,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let filters = image.filters.as_deref().unwrap_or_default();
+    match filters {
+        [f] if f == "DCTDecode" => {
+            fs::write(stem.with_extension("jpg"), image.content)?;
+            return Ok(true);
+        }
+        [f] if f == "JPXDecode" => {
+            fs::write(stem.with_extension("jp2"), image.content)?;
+            return Ok(true);
+        }
+        [] => {}
+        [f] if f == "FlateDecode" => {}
+        _ => return Ok(false),
+    }
+
+    let data = if filters.is_empty() {
+        image.content.to_vec()
+    } else {
+        doc.get_object(image.id)?.as_stream()?.decompressed_content()?
+    };
+    let (width, height) = (image.width as u32, image.height as u32);
+
+    let bpc = image.bits_per_component;
+    let Some(decoded) = decode_pixels(&data, width, height, bpc, image.color_space.as_deref())
+    else {
+        return Ok(false);
+    };
+    decoded.save(stem.with_extension("png"))?;
+    Ok(true)
+}
+
+/// Turns raw image samples into an `image` buffer.
+///
+/// Supports 8-bit gray, RGB and CMYK plus 1-bit gray. Color spaces that are not device spaces
+/// (ICCBased, Indexed, ...) are guessed from the sample count.
+fn decode_pixels(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    bits_per_component: Option<i64>,
+    color_space: Option<&str>,
+) -> Option<image::DynamicImage> {
+    let pixels = width as usize * height as usize;
+
+    if bits_per_component == Some(1) {
+        let row_bytes = (width as usize).div_ceil(8);
+        let gray: Vec<u8> = (0..pixels)
+            .map(|i| {
+                let (y, x) = (i / width as usize, i % width as usize);
+                let byte = data.get(y * row_bytes + x / 8).copied().unwrap_or(0);
+                if byte & (0x80 >> (x % 8)) != 0 { 255 } else { 0 }
+            })
+            .collect();
+        return image::GrayImage::from_raw(width, height, gray).map(image::DynamicImage::ImageLuma8);
+    }
+    if bits_per_component.is_some_and(|bpc| bpc != 8) {
+        return None;
+    }
+
+    let components = match color_space {
+        Some("DeviceGray") | Some("CalGray") => 1,
+        Some("DeviceRGB") | Some("CalRGB") => 3,
+        Some("DeviceCMYK") => 4,
+        _ => [1, 3, 4].into_iter().find(|n| data.len() == pixels * n)?,
+    };
+    let data = data.get(..pixels * components)?.to_vec();
+
+    match components {
+        1 => image::GrayImage::from_raw(width, height, data).map(image::DynamicImage::ImageLuma8),
+        3 => image::RgbImage::from_raw(width, height, data).map(image::DynamicImage::ImageRgb8),
+        _ => {
+            let rgb = data
+                .chunks_exact(4)
+                .flat_map(|cmyk| {
+                    let k = 255 - cmyk[3] as u16;
+                    cmyk[..3]
+                        .iter()
+                        .map(move |&c| ((255 - c as u16) * k / 255) as u8)
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            image::RgbImage::from_raw(width, height, rgb).map(image::DynamicImage::ImageRgb8)
+        }
+    }
+}
+
+/// Removes extracted images whose content duplicates an earlier one in the output directory.
+///
+/// Images are hashed with SHA-256 in parallel; for each hash the first path in sorted order is
+/// kept, so the same logo repeated on every page survives only once.
+///
+/// # Arguments
+///
+/// * `out_dir` - A reference to the path of the output directory.
+///
+/// # Returns
+///
+/// * `Result<usize, io::Error>` - The number of duplicates removed, or an I/O error on failure.
+fn remove_duplicate_images(out_dir: &Path) -> io::Result<usize> {
+    let mut files = Vec::new();
+    for dir_entry in fs::read_dir(out_dir)?.filter_map(Result::ok) {
+        if dir_entry.path().is_dir() {
+            files.extend(
+                fs::read_dir(dir_entry.path())?
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file()),
+            );
+        }
+    }
+    files.sort();
+
+    let hashed: Vec<(PathBuf, Vec<u8>)> = files
+        .into_par_iter()
+        .filter_map(|path| {
+            let content = fs::read(&path).ok()?;
+            Some((path, Sha256::digest(content).to_vec()))
+        })
+        .collect();
+
+    let mut seen: HashMap<Vec<u8>, PathBuf> = HashMap::new();
+    let mut dropped = 0;
+    for (path, hash) in hashed {
+        match seen.entry(hash) {
+            Entry::Occupied(_) => {
+                fs::remove_file(&path)?;
+                dropped += 1;
+            }
+            Entry::Vacant(slot) => {
+                slot.insert(path);
+            }
+        }
+    }
+
+    Ok(dropped)
+}
+
 /// Removes files smaller than the specified minimum size in the output directory.
 ///
 /// # Arguments
@@ -138,4 +540,24 @@ fn remove_small_files(out_dir: &PathBuf, min_size: u64) -> io::Result<()> {
         });
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_page_ranges_merges_and_keeps_open_end() {
+        let pages = parse_page_ranges("10, 1-5,4-6,20-").unwrap();
+        assert_eq!(pages, PageRanges(vec![(1, Some(6)), (10, Some(10)), (20, None)]));
+        assert!(pages.contains(6) && pages.contains(10) && pages.contains(500));
+        assert!(!pages.contains(7) && !pages.contains(19));
+    }
+
+    #[test]
+    fn test_parse_page_ranges_rejects_invalid_specs() {
+        assert!(parse_page_ranges("5-1").is_err());
+        assert!(parse_page_ranges("0-3").is_err());
+        assert!(parse_page_ranges("a").is_err());
+        assert!(parse_page_ranges(",").is_err());
+    }
+}
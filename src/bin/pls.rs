@@ -1,5 +1,8 @@
-use clap::{Parser, Subcommand};
-use image::GenericImageView;
+use clap::{Parser, Subcommand, ValueEnum};
+use image::{DynamicImage, GenericImageView, Pixel, Rgb};
+use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
+use prettytable::{row, Table};
+use rand::Rng;
 use rayon::prelude::*;
 use std::fs;
 use std::path::PathBuf;
@@ -25,6 +28,9 @@ enum Commands {
         /// Root directory containing images to classify.
         #[clap(short, long, required = true)]
         root_dir: PathBuf,
+        /// Optional CSV file to write per-image results to (path, is_grayscale, has_alpha).
+        #[clap(short, long)]
+        csv: Option<PathBuf>,
     },
     /// Check the difference between color channels of an image to determine its grayscale level.
     CheckDiff {
@@ -34,13 +40,16 @@ enum Commands {
         /// The threshold value for determining if an image is grayscale.
         #[clap(short, long, default_value_t = 0.02)]
         threshold: f64,
+        /// Estimate the channel difference from this many random pixels on images larger than that.
+        #[clap(long)]
+        sample: Option<usize>,
     },
 
     /// Extract images from a directory based on their type: grayscale, colorful, or with transparency.
     Extract {
-        /// Filter type: gsc (grayscale), col (colorful), tra (transparent), ntra (not transparent).
-        #[arg(short, long, default_value_t = String::from("gsc"))]
-        filter_type: String,
+        /// Which images to extract.
+        #[arg(short, long, value_enum, default_value_t = FilterType::Gsc)]
+        filter_type: FilterType,
         /// Input directory containing images to extract.
         #[clap(short, long, required = true)]
         input_dir: PathBuf,
@@ -50,6 +59,9 @@ enum Commands {
         /// The threshold value for determining if an image is grayscale.
         #[clap(short, long, default_value_t = 0.02)]
         threshold: f64,
+        /// Estimate the channel difference from this many random pixels on images larger than that.
+        #[clap(long)]
+        sample: Option<usize>,
     },
 
     /// Move images smaller than the specified size limit to the output directory.
@@ -63,18 +75,39 @@ enum Commands {
         /// Size limit of the image file, unit: Mb.
         #[clap(short, long, default_value_t = 0.2)]
         size: f64,
+        /// Also treat images narrower than this many pixels as small.
+        #[clap(long)]
+        min_width: Option<u32>,
+        /// Also treat images shorter than this many pixels as small.
+        #[clap(long)]
+        min_height: Option<u32>,
     },
 }
 
+/// The kind of image `Extract` picks out.
+#[derive(Clone, Copy, ValueEnum)]
+enum FilterType {
+    /// Grayscale images.
+    Gsc,
+    /// Colorful images.
+    Col,
+    /// Images with transparency.
+    Tra,
+    /// Images without transparency.
+    Ntra,
+}
+
 fn main() {
     let cli = Cli::parse();
 
     match &cli.command {
         Commands::Identify { images } => identify_images(images),
-        Commands::Classify { root_dir } => classify_images(root_dir),
-        Commands::CheckDiff { image, threshold } => check_diff(image, *threshold),
-        Commands::Extract { filter_type, input_dir, output_dir, threshold } => extract_images(filter_type, input_dir, output_dir.clone(), *threshold),
-        Commands::Small { input_dir, output_dir, size } => small_images(input_dir, output_dir.clone(), *size),
+        Commands::Classify { root_dir, csv } => classify_images(root_dir, csv.as_ref()),
+        Commands::CheckDiff { image, threshold, sample } => check_diff(image, *threshold, *sample),
+        Commands::Extract { filter_type, input_dir, output_dir, threshold, sample } => extract_images(*filter_type, input_dir, output_dir.clone(), *threshold, *sample),
+        Commands::Small { input_dir, output_dir, size, min_width, min_height } => {
+            small_images(input_dir, output_dir.clone(), *size, *min_width, *min_height)
+        }
     }
 }
 
@@ -97,15 +130,48 @@ fn identify_images(images: &[PathBuf]) {
     }
 }
 
-fn classify_images(root_dir: &PathBuf) {
-    // Placeholder implementation for classification
-    println!("Classifying images in {:?}", root_dir);
+fn classify_images(root_dir: &PathBuf, csv_path: Option<&PathBuf>) {
+    let mut results: Vec<(PathBuf, bool, bool)> = find_files_by_extensions_recursively(root_dir, &[".jpg", ".jpeg", ".png"])
+        .into_par_iter()
+        .filter_map(|img_path| match get_image_properties(&img_path) {
+            Ok((is_grayscale, has_alpha)) => Some((img_path, is_grayscale, has_alpha)),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        })
+        .collect();
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let grayscale = results.iter().filter(|(_, is_grayscale, _)| *is_grayscale).count();
+    let transparent = results.iter().filter(|(_, _, has_alpha)| *has_alpha).count();
+
+    let mut table = Table::new();
+    table.add_row(row!["Type", "Count"]);
+    table.add_row(row!["Grayscale", grayscale]);
+    table.add_row(row!["Colorful", results.len() - grayscale]);
+    table.add_row(row!["Transparent", transparent]);
+    table.add_row(row!["Opaque", results.len() - transparent]);
+    table.add_row(row!["Total", results.len()]);
+    println!("Classified images in {:?}", root_dir);
+    table.printstd();
+
+    if let Some(csv_path) = csv_path {
+        let mut wtr = csv::Writer::from_path(csv_path).expect("Failed to create CSV file");
+        wtr.write_record(["path", "is_grayscale", "has_alpha"]).expect("Failed to write CSV header");
+        for (path, is_grayscale, has_alpha) in &results {
+            wtr.write_record([path.display().to_string(), is_grayscale.to_string(), has_alpha.to_string()])
+                .expect("Failed to write CSV row");
+        }
+        wtr.flush().expect("Failed to flush CSV file");
+        println!("Results written to {}", csv_path.display());
+    }
 }
 
-fn check_diff(image: &PathBuf, threshold: f64) {
-    if let Ok(_) = get_image_properties(image) {
-        let gray_diff = calculate_gray_difference(image).unwrap_or(0.0);
-        let total_pixels = image::open(image).map(|img| img.width() * img.height()).unwrap_or(0);
+fn check_diff(image: &PathBuf, threshold: f64, sample: Option<usize>) {
+    if let Ok(img) = image::open(image) {
+        let gray_diff = gray_difference(&img, sample);
+        let total_pixels = img.width() * img.height();
         let grayscale_threshold = total_pixels as f64 * threshold;
         println!("The diff is {}", gray_diff - grayscale_threshold);
     } else {
@@ -113,40 +179,69 @@ fn check_diff(image: &PathBuf, threshold: f64) {
     }
 }
 
-fn extract_images(filter_type: &str, input_dir: &PathBuf, output_dir: Option<PathBuf>, threshold: f64) {
-    let output_dir = output_dir.unwrap_or_else(|| input_dir.join(format!("-{}", filter_type)));
+fn extract_images(filter_type: FilterType, input_dir: &PathBuf, output_dir: Option<PathBuf>, threshold: f64, sample: Option<usize>) {
+    let filter_name = filter_type.to_possible_value().expect("FilterType has no skipped variants");
+    let output_dir = output_dir.unwrap_or_else(|| input_dir.join(format!("-{}", filter_name.get_name())));
     fs::create_dir_all(&output_dir).expect("Failed to create output directory");
 
-    let filter_func: Box<dyn Fn(&PathBuf) -> bool> = match filter_type {
-        "gsc" => Box::new(move |p: &PathBuf| is_grayscale(p, threshold)),
-        "col" => Box::new(move |p: &PathBuf| !is_grayscale(p, threshold)),
-        "tra" => Box::new(is_transparent),
-        "ntra" => Box::new(move |p: &PathBuf| !is_transparent(p)),
-        _ => panic!("Invalid filter type"),
+    let filter_func: Box<dyn Fn(&PathBuf) -> bool + Sync> = match filter_type {
+        FilterType::Gsc => Box::new(move |p: &PathBuf| is_grayscale(p, threshold, sample)),
+        FilterType::Col => Box::new(move |p: &PathBuf| !is_grayscale(p, threshold, sample)),
+        FilterType::Tra => Box::new(is_transparent),
+        FilterType::Ntra => Box::new(move |p: &PathBuf| !is_transparent(p)),
     };
 
-    find_files_by_extensions_recursively(input_dir, &[".jpg", ".jpeg", ".png"])
-        .iter()
+    let images = find_files_by_extensions_recursively(input_dir, &[".jpg", ".jpeg", ".png"]);
+    let pb = ProgressBar::new(images.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("#>-"),
+    );
+
+    // Classify in parallel, but move serially: images from different subdirectories may share
+    // a file name, and concurrent moves would race for the same free destination.
+    let matched: Vec<PathBuf> = images
+        .into_par_iter()
+        .progress_with(pb.clone())
         .filter(|img_path| filter_func(img_path))
-        .for_each(|img_path| {
-            move_file_with_conflict_handling(&img_path, &output_dir);
-            println!("Extracted {:?}", img_path.strip_prefix(input_dir).unwrap());
-        });
+        .collect();
+    pb.finish_and_clear();
+
+    for img_path in matched {
+        move_file_with_conflict_handling(&img_path, &output_dir);
+        println!("Extracted {:?}", img_path.strip_prefix(input_dir).unwrap());
+    }
 }
 
-fn small_images(input_dir: &PathBuf, output_dir: Option<PathBuf>, size_limit_mb: f64) {
+fn small_images(input_dir: &PathBuf, output_dir: Option<PathBuf>, size_limit_mb: f64, min_width: Option<u32>, min_height: Option<u32>) {
     let output_dir = output_dir.unwrap_or_else(|| input_dir.join("-small"));
     fs::create_dir_all(&output_dir).expect("Failed to create output directory");
     let size_limit_bytes = (size_limit_mb * 1024.0 * 1024.0) as u64;
 
-    find_files_by_extensions_recursively(input_dir, &[".jpg", ".jpeg", ".png"])
+    let matched: Vec<PathBuf> = find_files_by_extensions_recursively(input_dir, &[".jpg", ".jpeg", ".png"])
         .into_par_iter()
-        .for_each(|img_path| {
-            if img_path.metadata().map(|m| m.len() < size_limit_bytes).unwrap_or(false) {
-                move_file_with_conflict_handling(&img_path, &output_dir);
-                println!("Moved {:?}", img_path.strip_prefix(input_dir).unwrap());
-            }
-        });
+        .filter(|img_path| {
+            let too_light = img_path.metadata().map(|m| m.len() < size_limit_bytes).unwrap_or(false);
+            let too_small = || {
+                if min_width.is_none() && min_height.is_none() {
+                    return false;
+                }
+                // Only reads the header, no full decode.
+                image::image_dimensions(img_path)
+                    .map(|(w, h)| min_width.is_some_and(|mw| w < mw) || min_height.is_some_and(|mh| h < mh))
+                    .unwrap_or(false)
+            };
+            too_light || too_small()
+        })
+        .collect();
+
+    // Moved serially for the same reason as in extract_images.
+    for img_path in matched {
+        move_file_with_conflict_handling(&img_path, &output_dir);
+        println!("Moved {:?}", img_path.strip_prefix(input_dir).unwrap());
+    }
 }
 
 fn get_image_properties(path: &PathBuf) -> Result<(bool, bool), String> {
@@ -156,28 +251,37 @@ fn get_image_properties(path: &PathBuf) -> Result<(bool, bool), String> {
     Ok((is_grayscale, has_alpha))
 }
 
-fn is_grayscale(path: &PathBuf, threshold: f64) -> bool {
+fn is_grayscale(path: &PathBuf, threshold: f64, sample: Option<usize>) -> bool {
     let img = image::open(path).expect("Error reading image");
-    let gray_diff = calculate_gray_difference(path).unwrap_or(0.0);
     let total_pixels = img.width() * img.height();
-    gray_diff < total_pixels as f64 * threshold
+    gray_difference(&img, sample) < total_pixels as f64 * threshold
 }
 
-fn calculate_gray_difference(path: &PathBuf) -> Result<f64, String> {
-    let img = image::open(path).map_err(|_| format!("Error reading image: {:?}", path))?;
-    let pixels: Vec<_> = img.pixels().collect();
-    let mut bg_diff = 0.0;
-    let mut gr_diff = 0.0;
-    let mut rb_diff = 0.0;
-
-    for i in 0..pixels.len() {
-        let [r, g, b, _] = pixels[i].2.0;
-        bg_diff += (r as f64 - g as f64).abs();
-        gr_diff += (g as f64 - b as f64).abs();
-        rb_diff += (r as f64 - b as f64).abs();
-    }
+/// Sums the absolute differences between color channels over all pixels.
+///
+/// With `sample` set and the image holding more pixels than that, only `sample`
+/// uniformly random pixels are evaluated and the sum is scaled by
+/// `total_pixels / sample`. The estimate stays on the same scale as the full sum,
+/// so comparing it against `total_pixels * threshold` remains valid.
+fn gray_difference(img: &DynamicImage, sample: Option<usize>) -> f64 {
+    // Compare in RGB regardless of the source color type, so alpha never enters the math.
+    let channel_diff = |pixel: Rgb<u8>| {
+        let [r, g, b] = pixel.0;
+        (r as f64 - g as f64).abs() + (g as f64 - b as f64).abs() + (r as f64 - b as f64).abs()
+    };
 
-    Ok(bg_diff + gr_diff + rb_diff)
+    let (width, height) = img.dimensions();
+    let total_pixels = width as usize * height as usize;
+    match sample {
+        Some(n) if n > 0 && total_pixels > n => {
+            let mut rng = rand::thread_rng();
+            let sampled: f64 = (0..n)
+                .map(|_| channel_diff(img.get_pixel(rng.gen_range(0..width), rng.gen_range(0..height)).to_rgb()))
+                .sum();
+            sampled * total_pixels as f64 / n as f64
+        }
+        _ => img.to_rgb8().pixels().map(|&pixel| channel_diff(pixel)).sum(),
+    }
 }
 
 fn is_transparent(path: &PathBuf) -> bool {
@@ -190,7 +294,8 @@ fn find_files_by_extensions_recursively(root_path: &PathBuf, extensions: &[&str]
     for entry in walkdir::WalkDir::new(root_path) {
         if let Ok(entry) = entry {
             let path = entry.path();
-            if path.is_file() && extensions.iter().any(|ext| path.extension().and_then(|e| e.to_str()) == Some(ext)) {
+            let extension = path.extension().and_then(|e| e.to_str());
+            if path.is_file() && extensions.iter().any(|ext| extension == Some(ext.trim_start_matches('.'))) {
                 files.push(path.to_path_buf());
             }
         }
@@ -214,5 +319,29 @@ fn move_file_with_conflict_handling(src_path: &PathBuf, dst_dir: &PathBuf) {
     fs::rename(src_path, &dst_path).expect("Failed to move file");
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma, RgbImage};
 
+    #[test]
+    fn test_grayscale_png_has_near_zero_diff() {
+        let path = std::env::temp_dir().join(format!("pls_gray_{}.png", std::process::id()));
+        GrayImage::from_fn(64, 48, |x, y| Luma([((x * 4 + y) % 256) as u8]))
+            .save(&path)
+            .expect("Failed to write test image");
 
+        let img = image::open(&path).expect("Failed to read test image");
+        fs::remove_file(&path).ok();
+
+        assert_eq!(img.color(), image::ColorType::L8);
+        assert!(gray_difference(&img, None) < 1e-9);
+    }
+
+    #[test]
+    fn test_rgb_layout_matches_channel_math() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 2, Rgb([10, 20, 40])));
+        // |10-20| + |20-40| + |10-40| = 60 per pixel
+        assert_eq!(gray_difference(&img, None), 240.0);
+    }
+}
@@ -1,11 +1,22 @@
+use clap::builder::RangedU64ValueParser;
 use clap::{Arg, ArgAction, Command};
+use std::collections::HashMap;
 use std::{env, fs};
 use std::fs::{remove_dir_all};
 use std::path::{Path, PathBuf};
-use image::{open, GenericImageView, ImageFormat};
+use image::ImageFormat;
 use walkdir::WalkDir;
 use colored::Colorize;
 use rayon::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    // aspect_min_max buckets from --ratios
+    Ratio,
+    // portrait / landscape / square buckets
+    Orientation,
+}
 
 struct AppArgs {
     input_dir: PathBuf,
@@ -15,6 +26,19 @@ struct AppArgs {
     verbose: bool,
     ratios: Vec<(f32, f32)>,
     threads: usize,
+    mode: Mode,
+    square_tol: f32,
+    manifest: Option<PathBuf>,
+}
+
+// One classified image, written to the --manifest file
+#[derive(Serialize)]
+struct ManifestEntry {
+    original_path: PathBuf,
+    // 无法读取尺寸的图片为空
+    aspect_ratio: Option<f32>,
+    bucket: String,
+    new_path: PathBuf,
 }
 // Function to parse command line arguments
 fn parse_args() -> AppArgs {
@@ -53,13 +77,31 @@ fn parse_args() -> AppArgs {
             .long("ratios")
             .value_name("RATIOS")
             .help("Comma-separated list of aspect ratio ranges in format 'min:max'")
+            .value_delimiter(',')
             .default_values(vec!["0:1", "1:8"]))
         .arg(Arg::new("threads")
             .short('t')
             .long("threads")
             .value_name("NUM")
             .help("Number of threads to use for parallel processing")
-            .default_value("N"))
+            .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+            .default_value(num_cpus::get().to_string()))
+        .arg(Arg::new("mode")
+            .long("mode")
+            .value_name("MODE")
+            .help("Bucket by the --ratios ranges or by orientation (portrait/landscape/square)")
+            .value_parser(["ratio", "orientation"])
+            .default_value("ratio"))
+        .arg(Arg::new("square-tol")
+            .long("square-tol")
+            .value_name("TOL")
+            .help("In orientation mode, ratios within this distance of 1 count as square")
+            .value_parser(clap::value_parser!(f32))
+            .default_value("0.01"))
+        .arg(Arg::new("manifest")
+            .long("manifest")
+            .value_name("PATH")
+            .help("Write where each image went to this file, as JSON if it ends in .json, otherwise CSV"))
         .get_matches();
 
     let input_dir = matches.get_one::<String>("input-dir").unwrap().as_abs();
@@ -67,7 +109,7 @@ fn parse_args() -> AppArgs {
     let move_files = matches.get_flag("move-files");
     let clean_empty = matches.get_flag("clean-empty");
     let verbose = matches.get_flag("verbose");
-    let ratios = matches.get_many::<String>("ratios").unwrap_or_default()
+    let ratios: Vec<(f32, f32)> = matches.get_many::<String>("ratios").unwrap_or_default()
         .map(|ratio_str| {
             let parts: Vec<&str> = ratio_str.split(':').collect();
             let min = parts[0].parse::<f32>().unwrap_or(f32::MIN);
@@ -75,10 +117,43 @@ fn parse_args() -> AppArgs {
             (min, max)
         })
         .collect();
-    let customized_threads = matches.get_one::<String>("threads").unwrap();
-    let threads = if customized_threads != "N" { customized_threads.parse::<usize>().unwrap() } else { num_cpus::get() };
-    AppArgs { input_dir, output_dir, move_files, clean_empty, verbose, ratios, threads }
+    let threads = *matches.get_one::<usize>("threads").unwrap();
+    let mode = match matches.get_one::<String>("mode").unwrap().as_str() {
+        "orientation" => Mode::Orientation,
+        _ => Mode::Ratio,
+    };
+    let square_tol = *matches.get_one::<f32>("square-tol").unwrap();
+    let manifest = matches.get_one::<String>("manifest").map(|path| path.as_abs());
+    if mode == Mode::Ratio {
+        warn_overlapping_ratios(&ratios);
+    }
+    AppArgs { input_dir, output_dir, move_files, clean_empty, verbose, ratios, threads, mode, square_tol, manifest }
 }
+// Ranges are half-open [min, max), an image goes to the first one that matches
+fn warn_overlapping_ratios(ratios: &[(f32, f32)]) {
+    for (i, a) in ratios.iter().enumerate() {
+        for b in &ratios[i + 1..] {
+            if a.0 < b.1 && b.0 < a.1 {
+                println!("{}", format!("Warning: ratio ranges {}:{} and {}:{} overlap, images in both go to {}:{}", a.0, a.1, b.0, b.1, a.0, a.1).yellow());
+            }
+        }
+    }
+}
+
+fn orientation_bucket(aspect_ratio: f32, square_tol: f32) -> &'static str {
+    if (aspect_ratio - 1.0).abs() <= square_tol {
+        "square"
+    } else if aspect_ratio < 1.0 {
+        "portrait"
+    } else {
+        "landscape"
+    }
+}
+
+fn bucket_name(ratio: &(f32, f32)) -> String {
+    format!("aspect_{}_{}", ratio.0, ratio.1)
+}
+
 // 定义一个新的 trait AsAbsPath
 trait AsAbsPath {
     fn as_abs(&self) -> PathBuf;
@@ -108,20 +183,29 @@ impl AsAbsPath for String {
         absolute_path
     }
 }
-// Function to classify an image and move/copy it to the appropriate directory
-fn classify_image(path: &Path, input_dir: &Path, output_dir: &Path, move_files: bool, verbose: bool, ratios: &[(f32, f32)]) -> Result<(), Box<dyn std::error::Error>> {
-    let img = open(path)?;
-    let (width, height) = img.dimensions();
-    let aspect_ratio = width as f32 / height as f32;
-
-    let found = ratios.iter().position(|&(min, max)| aspect_ratio >= min && aspect_ratio < max);
-
-    let target_relative_dir = match found {
-        Some(index) => {
-            let ratio = ratios.get(index).unwrap();
-            format!("aspect_{}_{}", ratio.0, ratio.1)
+// Function to classify an image and move/copy it to the appropriate directory, returning where it went
+fn classify_image(path: &Path, args: &AppArgs) -> Result<ManifestEntry, Box<dyn std::error::Error>> {
+    let (input_dir, output_dir, move_files, verbose) = (args.input_dir.as_path(), args.output_dir.as_path(), args.move_files, args.verbose);
+    // 只读取文件头获取尺寸，读取失败的文件放入 unreadable 便于检查
+    let dimensions = image::image_dimensions(path);
+    let aspect_ratio = match dimensions {
+        Ok((width, height)) => width as f32 / height as f32,
+        Err(ref e) => {
+            eprintln!("Failed to read dimensions of {}: {}", path.display(), e);
+            f32::NAN
+        }
+    };
+
+    let target_relative_dir = match (dimensions.is_ok(), args.mode) {
+        (false, _) => String::from("unreadable"),
+        (true, Mode::Ratio) => {
+            let found = args.ratios.iter().position(|&(min, max)| aspect_ratio >= min && aspect_ratio < max);
+            match found {
+                Some(index) => bucket_name(&args.ratios[index]),
+                None => String::from("other"),
+            }
         }
-        None => String::from("other"),
+        (true, Mode::Orientation) => String::from(orientation_bucket(aspect_ratio, args.square_tol)),
     };
 
     let relative_path_from_input = path.strip_prefix(input_dir).unwrap();
@@ -146,10 +230,29 @@ fn classify_image(path: &Path, input_dir: &Path, output_dir: &Path, move_files:
                 fs::copy(path, &target_path)?;
                 fs::remove_file(path)?;
             }
-            Err(e) => println!("Error moving file: {:?}", e), // 其他错误
+            Err(e) => return Err(format!("Error moving file: {:?}", e).into()), // 其他错误
         }
     }
 
+    Ok(ManifestEntry {
+        original_path: path.to_path_buf(),
+        aspect_ratio: dimensions.is_ok().then_some(aspect_ratio),
+        bucket: target_relative_dir,
+        new_path: target_path,
+    })
+}
+
+// Function to write the manifest as JSON or CSV depending on the file extension
+fn write_manifest(path: &Path, entries: &[ManifestEntry]) -> Result<(), Box<dyn std::error::Error>> {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        fs::write(path, serde_json::to_string_pretty(entries)?)?;
+    } else {
+        let mut writer = csv::Writer::from_path(path)?;
+        for entry in entries {
+            writer.serialize(entry)?;
+        }
+        writer.flush()?;
+    }
     Ok(())
 }
 
@@ -203,9 +306,13 @@ fn main() {
     println!("Move Files: {}", if app_args.move_files { "Yes".red() } else { "No".yellow() });
     println!("Clean Empty Directories: {}", if app_args.clean_empty { "Yes".red() } else { "No".yellow() });
     println!("Verbose Mode: {}", if app_args.verbose { "Enabled" } else { "Disabled" });
-    println!("Aspect Ratio Ranges:");
-    for (index, ratio) in app_args.ratios.iter().enumerate() {
-        println!("  Range {}: {:.2}:{:.2}", index, ratio.0, ratio.1);
+    if app_args.mode == Mode::Orientation {
+        println!("Mode: orientation (square tolerance {})", app_args.square_tol);
+    } else {
+        println!("Aspect Ratio Ranges:");
+        for (index, ratio) in app_args.ratios.iter().enumerate() {
+            println!("  Range {}: {:.2}:{:.2}", index, ratio.0, ratio.1);
+        }
     }
 
     // 确保输出目录存在
@@ -219,18 +326,51 @@ fn main() {
     rayon::ThreadPoolBuilder::new().num_threads(app_args.threads).build_global().unwrap();
 
     // 并行遍历目录中的每个文件
-    WalkDir::new(&app_args.input_dir)
+    let mut entries: Vec<ManifestEntry> = WalkDir::new(&app_args.input_dir)
         .into_iter()
         .par_bridge()
         .filter_map(Result::ok)
-        .for_each(|entry| {
+        .filter_map(|entry| {
             let path = entry.path();
             if path.is_file() && ImageFormat::from_path(path).is_ok() {
                 // 注意这里不能直接返回Result，因为Rayon无法处理异步错误
-                classify_image(path, &app_args.input_dir, &app_args.output_dir, app_args.move_files, app_args.verbose, &app_args.ratios)
-                    .unwrap_or_else(|e| eprintln!("Failed to process file: {}", e));
+                classify_image(path, &app_args)
+                    .map_err(|e| eprintln!("Failed to process file: {}", e))
+                    .ok()
+            } else {
+                None
             }
-        });
+        })
+        .collect();
+
+    // 统计每个分类的数量
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for entry in &entries {
+        *counts.entry(entry.bucket.as_str()).or_default() += 1;
+    }
+    println!("{}", "Classification summary:".green());
+    let mut names: Vec<String> = match app_args.mode {
+        Mode::Ratio => {
+            let mut names: Vec<String> = app_args.ratios.iter().map(bucket_name).collect();
+            names.dedup();
+            names.push(String::from("other"));
+            names
+        }
+        Mode::Orientation => ["portrait", "landscape", "square"].map(String::from).to_vec(),
+    };
+    names.push(String::from("unreadable"));
+    for name in &names {
+        println!("  {}: {}", name, counts.get(name.as_str()).copied().unwrap_or(0).to_string().blue());
+    }
+
+    if let Some(manifest) = &app_args.manifest {
+        // 并行遍历的顺序不固定，按原路径排序后再写出
+        entries.sort_by(|a, b| a.original_path.cmp(&b.original_path));
+        match write_manifest(manifest, &entries) {
+            Ok(()) => println!("Manifest written to {}", manifest.display().to_string().magenta()),
+            Err(e) => eprintln!("Failed to write manifest {}: {}", manifest.display(), e),
+        }
+    }
 
     if app_args.clean_empty {
         println!("Starting cleanup...");
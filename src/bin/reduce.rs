@@ -1,4 +1,8 @@
 use clap::Parser;
+use glob::Pattern;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self};
 use std::path::{Path, PathBuf};
 
@@ -29,7 +33,7 @@ struct Cli {
     ///
     /// - `override`: Overwrites the existing file or directory at the destination.
     ///
-    /// - `halt`: Stops execution if a collision is detected.
+    /// - `halt`: Stops before moving anything if a collision is detected.
     #[arg(short, long, default_value = "auto")]
     collision_strategy: String,
 
@@ -44,13 +48,48 @@ struct Cli {
     /// - Action: Flagged presence sets this to `true`; absence sets it to `false`.
     #[arg(short, long, action)]
     verbose: bool,
+
+    /// Prints every planned move and collision resolution without touching the filesystem.
+    #[arg(short = 'n', long, action)]
+    dry_run: bool,
+
+    /// Removes the source instead of moving it when it collides with a file of identical content.
+    ///
+    /// Files are compared by size first and by SHA-256 digest when the sizes match.
+    #[arg(long, action)]
+    dedup: bool,
+
+    /// Only moves entries whose name matches one of these glob patterns, e.g. `*.jpg`.
+    ///
+    /// May be repeated; everything is moved when no pattern is given.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<Pattern>,
+
+    /// Leaves entries whose name matches one of these glob patterns in place.
+    ///
+    /// May be repeated, and takes precedence over `--include`.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<Pattern>,
+}
+
+/// A single filesystem operation computed during planning.
+enum MoveOp {
+    /// Moves `src` to `dest`, removing whatever already sits at `dest` when `replace` is set.
+    Move {
+        src: PathBuf,
+        dest: PathBuf,
+        replace: bool,
+    },
+    /// Removes `src`, used when a later source overrides its planned destination or
+    /// when `src` duplicates the file at its destination.
+    Remove(PathBuf),
 }
 
-fn rename_if_exists(path: &Path) -> PathBuf {
+fn rename_if_exists(path: &Path, taken: &HashSet<PathBuf>) -> PathBuf {
     let mut i = 1;
     let mut new_path = path.to_path_buf();
-    while new_path.exists() {
-        new_path.set_file_name(format!(
+    while new_path.exists() || taken.contains(&new_path) {
+        new_path = path.with_file_name(format!(
             "{}_{}",
             path.file_stem().unwrap().to_string_lossy(),
             i
@@ -60,23 +99,16 @@ fn rename_if_exists(path: &Path) -> PathBuf {
     }
     new_path
 }
-fn handle_collision(
+
+fn auto_strategy(
     src_path: &PathBuf,
     dest_path: &PathBuf,
-    collision_strategy: &str,
+    taken: &HashSet<PathBuf>,
     verbose: bool,
+    dry_run: bool,
 ) -> Option<PathBuf> {
-    match collision_strategy.to_lowercase().as_str() {
-        "auto" => auto_strategy(src_path, dest_path, verbose),
-        "override" => override_strategy(dest_path, verbose),
-        "halt" => halt_strategy(dest_path),
-        _ => unreachable!("Unknown collision strategy"),
-    }
-}
-
-fn auto_strategy(src_path: &PathBuf, dest_path: &PathBuf, verbose: bool) -> Option<PathBuf> {
     if dest_path.exists() && src_path.canonicalize().ok() == dest_path.canonicalize().ok() {
-        if verbose {
+        if verbose || dry_run {
             println!(
                 "Skipping {} as it matches {}.",
                 src_path.display(),
@@ -86,9 +118,9 @@ fn auto_strategy(src_path: &PathBuf, dest_path: &PathBuf, verbose: bool) -> Opti
         return None;
     }
 
-    if dest_path.exists() {
-        let new_dest_path = rename_if_exists(dest_path);
-        if verbose {
+    if dest_path.exists() || taken.contains(dest_path) {
+        let new_dest_path = rename_if_exists(dest_path, taken);
+        if verbose || dry_run {
             println!(
                 "Renaming {} to {} to avoid collision.",
                 dest_path.display(),
@@ -100,53 +132,195 @@ fn auto_strategy(src_path: &PathBuf, dest_path: &PathBuf, verbose: bool) -> Opti
         Some(dest_path.to_path_buf())
     }
 }
-fn halt_strategy(dest_path: &PathBuf) -> Option<PathBuf> {
+
+fn halt_strategy(dest_path: &PathBuf, taken: &HashSet<PathBuf>) -> Result<PathBuf, String> {
     if dest_path.exists() {
-        panic!("Destination path {} already exists.", dest_path.display());
+        return Err(format!(
+            "Destination path {} already exists.",
+            dest_path.display()
+        ));
     }
-    Some(dest_path.to_path_buf())
+    if taken.contains(dest_path) {
+        return Err(format!(
+            "Destination path {} is the target of more than one move.",
+            dest_path.display()
+        ));
+    }
+    Ok(dest_path.to_path_buf())
 }
-fn override_strategy(dest_path: &PathBuf, verbose: bool) -> Option<PathBuf> {
-    if dest_path.exists() {
-        if dest_path.is_dir() {
-            fs::remove_dir_all(dest_path).expect("Failed to remove directory");
+
+fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Whether `a` and `b` are two distinct files with the same content.
+fn is_duplicate(a: &Path, b: &Path) -> bool {
+    if !a.is_file() || !b.is_file() || a.canonicalize().ok() == b.canonicalize().ok() {
+        return false;
+    }
+    let same_size = match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) => ma.len() == mb.len(),
+        _ => false,
+    };
+    same_size
+        && match (hash_file(a), hash_file(b)) {
+            (Ok(ha), Ok(hb)) => ha == hb,
+            _ => false,
+        }
+}
+
+/// Whether the file name of `path` passes the `--include` and `--exclude` patterns.
+fn is_selected(path: &Path, include: &[Pattern], exclude: &[Pattern]) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (include.is_empty() || include.iter().any(|p| p.matches(&name)))
+        && !exclude.iter().any(|p| p.matches(&name))
+}
+
+/// Collects every entry sitting `depth` levels below `input_dir`, sorted so planning is deterministic.
+fn collect_sources(input_dir: &Path, depth: u32, include_hidden: bool) -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    let mut queue = vec![(input_dir.to_path_buf(), depth)];
+
+    while let Some((current_dir, current_depth)) = queue.pop() {
+        let entries = fs::read_dir(&current_dir)
+            .expect("Failed to read directory")
+            .filter_map(Result::ok)
+            .filter(|e| include_hidden || !e.file_name().to_string_lossy().starts_with('.'));
+
+        if current_depth == 0 {
+            sources.extend(entries.map(|e| e.path()));
         } else {
-            fs::remove_file(dest_path).expect("Failed to remove file");
+            for dir in entries.filter(|e| e.path().is_dir()) {
+                queue.push((dir.path(), current_depth - 1));
+            }
         }
-        if verbose {
-            println!(
-                "Overriding {} with a new file or directory.",
-                dest_path.display()
-            );
+    }
+
+    sources.sort();
+    sources
+}
+
+/// Resolves the destination of every source before anything is moved.
+///
+/// Destinations claimed by earlier sources count as collisions, so auto-renamed targets
+/// never race each other during the parallel execution.
+fn plan_moves(
+    sources: Vec<PathBuf>,
+    output_dir: &Path,
+    collision_strategy: &str,
+    dedup: bool,
+    verbose: bool,
+    dry_run: bool,
+) -> (Vec<MoveOp>, Vec<String>) {
+    let mut ops = Vec::new();
+    let mut halts = Vec::new();
+    let mut claimed: HashMap<PathBuf, usize> = HashMap::new();
+    let mut taken: HashSet<PathBuf> = HashSet::new();
+
+    for src in sources {
+        let dest = output_dir.join(src.file_name().unwrap());
+        if dedup {
+            // A planned source will occupy `dest` once moved, otherwise whatever is on disk does.
+            let occupant = match claimed.get(&dest).map(|&index| &ops[index]) {
+                Some(MoveOp::Move { src: planned, .. }) => Some(planned.clone()),
+                _ => dest.exists().then(|| dest.clone()),
+            };
+            if let Some(occupant) = occupant.filter(|o| is_duplicate(&src, o)) {
+                if verbose || dry_run {
+                    println!(
+                        "{} duplicates {}, removing it instead.",
+                        src.display(),
+                        occupant.display()
+                    );
+                }
+                ops.push(MoveOp::Remove(src));
+                continue;
+            }
+        }
+
+        let resolved = match collision_strategy.to_lowercase().as_str() {
+            "auto" => auto_strategy(&src, &dest, &taken, verbose, dry_run).map(|d| (d, false)),
+            "override" => {
+                if let Some(&index) = claimed.get(&dest) {
+                    if let MoveOp::Move { src: shadowed, .. } = &ops[index] {
+                        if verbose || dry_run {
+                            println!(
+                                "{} overrides {} at {}.",
+                                src.display(),
+                                shadowed.display(),
+                                dest.display()
+                            );
+                        }
+                        ops[index] = MoveOp::Remove(shadowed.clone());
+                    }
+                }
+                Some((dest, true))
+            }
+            "halt" => match halt_strategy(&dest, &taken) {
+                Ok(d) => Some((d, false)),
+                Err(halt) => {
+                    halts.push(halt);
+                    None
+                }
+            },
+            _ => unreachable!("Unknown collision strategy"),
+        };
+
+        if let Some((dest, replace)) = resolved {
+            claimed.insert(dest.clone(), ops.len());
+            taken.insert(dest.clone());
+            ops.push(MoveOp::Move { src, dest, replace });
         }
     }
-    Some(dest_path.to_path_buf())
+    (ops, halts)
 }
-fn resolve_move(src_path: &PathBuf, dest_path: &PathBuf, verbose: bool) {
-    fs::rename(src_path, dest_path).expect("Failed to move file/directory");
-    if verbose {
-        println!("Moved {} to {}.", src_path.display(), dest_path.display());
+
+fn remove_path(path: &Path) {
+    if path.is_dir() {
+        fs::remove_dir_all(path).expect("Failed to remove directory");
+    } else {
+        fs::remove_file(path).expect("Failed to remove file");
     }
 }
 
-fn move_dir_content(
-    src_dir: &PathBuf,
-    dest_dir_path: &PathBuf,
-    verbose: bool,
-    strategy: &str,
-    include_hidden: bool,
-) {
-    fs::read_dir(src_dir)
-        .expect("Failed to read directory")
-        .filter_map(Result::ok)
-        .filter(|e| include_hidden || !e.file_name().to_string_lossy().starts_with('.'))
-        .for_each(|entry| {
-            let dest_f = dest_dir_path.join(entry.file_name());
-            if let Some(handled_dest) = handle_collision(&entry.path(), &dest_f, strategy, verbose)
-            {
-                resolve_move(&entry.path(), &handled_dest, verbose);
+fn execute_op(op: &MoveOp, verbose: bool, dry_run: bool) {
+    match op {
+        MoveOp::Move { src, dest, replace } => {
+            if dry_run {
+                if *replace && dest.exists() {
+                    println!("Would override {}.", dest.display());
+                }
+                println!("Would move {} to {}.", src.display(), dest.display());
+                return;
             }
-        });
+            if *replace && dest.exists() {
+                remove_path(dest);
+                if verbose {
+                    println!(
+                        "Overriding {} with a new file or directory.",
+                        dest.display()
+                    );
+                }
+            }
+            fs::rename(src, dest).expect("Failed to move file/directory");
+            if verbose {
+                println!("Moved {} to {}.", src.display(), dest.display());
+            }
+        }
+        MoveOp::Remove(src) => {
+            if dry_run {
+                println!("Would remove {}.", src.display());
+                return;
+            }
+            remove_path(src);
+            if verbose {
+                println!("Removed {}.", src.display());
+            }
+        }
+    }
 }
 
 fn is_directory_empty(directory: &PathBuf) -> bool {
@@ -155,42 +329,36 @@ fn is_directory_empty(directory: &PathBuf) -> bool {
         .unwrap_or(false)
 }
 
-fn expand_directories(
-    input_dir: &PathBuf,
-    output_dir: &Path,
-    depth: u32,
-    collision_strategy: &str,
-    include_hidden: bool,
-    verbose: bool,
-) {
-    let initial_depth = depth;
-    let mut queue = vec![(input_dir.clone(), output_dir, initial_depth)];
+/// Plans every move, executes them in parallel and cleans up the emptied directories.
+///
+/// Returns the `halt` collisions found while planning; nothing is moved when there are any.
+fn expand_directories(cli: &Cli) -> Vec<String> {
+    let verbose = cli.verbose;
+    let dry_run = cli.dry_run;
+    let sources: Vec<PathBuf> = collect_sources(&cli.input_dir, cli.depth, cli.include_hidden)
+        .into_iter()
+        .filter(|src| is_selected(src, &cli.include, &cli.exclude))
+        .collect();
+    let (ops, halts) = plan_moves(
+        sources,
+        &cli.output_dir,
+        &cli.collision_strategy,
+        cli.dedup,
+        verbose,
+        dry_run,
+    );
+    if !halts.is_empty() {
+        return halts;
+    }
 
-    while let Some((current_input_dir, current_output_dir, current_depth)) = queue.pop() {
-        if current_depth == 0 {
-            move_dir_content(
-                &current_input_dir,
-                &current_output_dir.to_path_buf(),
-                verbose,
-                collision_strategy,
-                include_hidden,
-            );
-        } else {
-            let dirs_to_move: Vec<_> = fs::read_dir(&current_input_dir)
-                .expect("Failed to read directory")
-                .filter_map(Result::ok)
-                .filter(|e| e.path().is_dir())
-                .filter(|e| include_hidden || !e.file_name().to_string_lossy().starts_with('.'))
-                .collect();
-
-            let remaining_depth = current_depth - 1;
-            for dir in dirs_to_move {
-                queue.push((dir.path(), current_output_dir, remaining_depth));
-            }
-        }
+    if dry_run {
+        ops.iter().for_each(|op| execute_op(op, verbose, true));
+        return halts;
     }
+    ops.into_par_iter()
+        .for_each(|op| execute_op(&op, verbose, false));
 
-    fs::read_dir(input_dir)
+    fs::read_dir(&cli.input_dir)
         .expect("Failed to read directory")
         .filter_map(Result::ok)
         .filter(|entry| entry.path().is_dir() && is_directory_empty(&entry.path()))
@@ -200,6 +368,7 @@ fn expand_directories(
                 println!("Cleaning {}", entry.path().display());
             }
         });
+    halts
 }
 
 fn main() {
@@ -210,12 +379,12 @@ fn main() {
         return;
     }
 
-    expand_directories(
-        &cli.input_dir,
-        &cli.output_dir,
-        cli.depth,
-        &cli.collision_strategy,
-        cli.include_hidden,
-        cli.verbose,
-    );
+    let halts = expand_directories(&cli);
+
+    if !halts.is_empty() {
+        for halt in &halts {
+            eprintln!("Halted: {}", halt);
+        }
+        std::process::exit(1);
+    }
 }
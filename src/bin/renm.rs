@@ -1,9 +1,12 @@
 use clap::Parser;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
 
 /// A Tool that renames files in a directory and manages mappings.
 #[derive(Parser, Debug)]
@@ -24,69 +27,261 @@ struct Args {
     /// Restore filenames using the specified mapping file
     #[arg(short, long, conflicts_with = "output")]
     restore: Option<String>,
+
+    /// Also rename files in subdirectories
+    #[arg(short = 'R', long)]
+    recursive: bool,
+
+    /// Restart numbering in every directory instead of numbering all files globally
+    #[arg(long, requires = "recursive")]
+    per_dir: bool,
+
+    /// Zero-pad numbers to this width; without a value the width of the largest number is used
+    #[arg(short, long, num_args = 0..=1, default_missing_value = "0")]
+    pad: Option<usize>,
+
+    /// Print every planned rename and all conflicts without renaming anything
+    #[arg(long, conflicts_with = "restore")]
+    dry_run: bool,
+
+    /// Check each file's size and CRC32 against the mapping before restoring it
+    #[arg(long, requires = "restore")]
+    verify: bool,
+}
+
+/// Version written to new mapping files.
+const MAPPING_VERSION: u32 = 3;
+
+/// Mapping file contents.
+///
+/// The map is keyed by the old path relative to the processed directory. Since
+/// version 3 every value is a [`MappingEntry`]; version 2 stored the new relative
+/// path as a plain string. Files written before versioning are a flat
+/// `old_name -> new_name` object. Both older layouts are still accepted when restoring.
+#[derive(Serialize, Deserialize, Debug)]
+struct Mapping {
+    version: u32,
+    files: BTreeMap<String, MappingEntry>,
+}
+
+/// Where a file was renamed to, plus its size and CRC32 for `--verify`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(from = "StoredEntry")]
+struct MappingEntry {
+    new_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crc32: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredEntry {
+    Full {
+        new_name: String,
+        size: Option<u64>,
+        crc32: Option<u32>,
+    },
+    Name(String),
+}
+
+impl From<StoredEntry> for MappingEntry {
+    fn from(entry: StoredEntry) -> Self {
+        match entry {
+            StoredEntry::Full {
+                new_name,
+                size,
+                crc32,
+            } => MappingEntry {
+                new_name,
+                size,
+                crc32,
+            },
+            StoredEntry::Name(new_name) => MappingEntry {
+                new_name,
+                size: None,
+                crc32: None,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MappingFile {
+    Versioned(Mapping),
+    Legacy(HashMap<String, String>),
+}
+
+/// A single planned rename, with paths relative to the processed directory.
+#[derive(Debug)]
+struct RenameOp {
+    old_path: PathBuf,
+    new_path: PathBuf,
+    old_name: String,
+    new_name: String,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
     if let Some(restore_map) = args.restore.clone() {
-        restore_files(&args.directory, &restore_map)?;
+        restore_files(&args.directory, &restore_map, args.verify)?;
     } else {
-        rename_files(&args.directory, &args.output)?;
+        rename_files(&args)?;
     }
 
     Ok(())
 }
 
-fn rename_files(directory: &str, output: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let entries = fs::read_dir(directory)?;
-    let mut files: Vec<_> = entries
+fn rename_files(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let rename_ops = plan_renames(
+        Path::new(&args.directory),
+        args.recursive,
+        args.per_dir,
+        args.pad,
+    )?;
+
+    if args.dry_run {
+        println!("Planned renames:");
+        for op in &rename_ops {
+            println!("{} -> {}", op.old_name, op.new_name);
+        }
+    }
+
+    let conflicts = check_conflicts(&rename_ops)?;
+    if !conflicts.is_empty() {
+        eprintln!("\nError: {} destination(s) already exist:", conflicts.len());
+        for path in &conflicts {
+            eprintln!("- {}", path.display());
+        }
+        return Err(format!("{} conflicts found, nothing was renamed", conflicts.len()).into());
+    }
+
+    if args.dry_run {
+        println!("\nDry run: {} files would be renamed", rename_ops.len());
+        return Ok(());
+    }
+
+    perform_renames(&rename_ops)?;
+    save_mapping(&rename_ops, &args.output)?;
+    Ok(())
+}
+
+/// Collect the files to rename and assign their new numbered names.
+///
+/// `pad` is the zero-padding width, `Some(0)` derives it from the largest number.
+fn plan_renames(
+    dir: &Path,
+    recursive: bool,
+    per_dir: bool,
+    pad: Option<usize>,
+) -> Result<Vec<RenameOp>, Box<dyn std::error::Error>> {
+    let mut files: Vec<PathBuf> = WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(if recursive { usize::MAX } else { 1 })
+        .into_iter()
         .filter_map(Result::ok)
-        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
         .collect();
 
-    files.sort_by(|a, b| a.file_name().to_str().cmp(&b.file_name().to_str()));
+    files.sort();
+
+    let mut counters: HashMap<PathBuf, usize> = HashMap::new();
+    let numbers: Vec<usize> = files
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            if per_dir {
+                let parent = path.parent().unwrap_or(dir).to_path_buf();
+                let counter = counters.entry(parent).or_insert(0);
+                *counter += 1;
+                *counter
+            } else {
+                i + 1
+            }
+        })
+        .collect();
+
+    let width = match pad {
+        None => 0,
+        Some(0) => numbers.iter().max().map_or(1, |n| n.to_string().len()),
+        Some(width) => width,
+    };
 
     let mut rename_ops = Vec::new();
-    for (i, entry) in files.iter().enumerate() {
-        let old_name = entry.file_name().to_str().unwrap().to_string();
-        let extension: String = entry
-            .path()
+    for (path, number) in files.into_iter().zip(numbers) {
+        let number = format!("{:0width$}", number, width = width);
+
+        let extension: String = path
             .extension()
             .and_then(|s| s.to_str().map(|s| s.to_owned()))
             .unwrap_or_default();
 
-        let new_name = if extension.is_empty() {
-            (i + 1).to_string()
+        let new_file_name = if extension.is_empty() {
+            number
         } else {
-            format!("{}.{}", i + 1, extension)
+            format!("{}.{}", number, extension)
         };
 
-        let new_path = entry.path().with_file_name(&new_name);
-        rename_ops.push((entry.path().to_path_buf(), new_path, old_name));
+        let new_path = path.with_file_name(&new_file_name);
+        rename_ops.push(RenameOp {
+            old_name: relative_name(dir, &path)?,
+            new_name: relative_name(dir, &new_path)?,
+            old_path: path,
+            new_path,
+        });
     }
 
-    check_conflicts(&rename_ops)?;
-    perform_renames(&rename_ops)?;
-    save_mapping(&rename_ops, output)?;
-    Ok(())
+    Ok(rename_ops)
 }
 
-fn restore_files(directory: &str, restore_map: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// `path` relative to `dir` with `/` separators, as stored in the mapping file.
+fn relative_name(dir: &Path, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let relative = path.strip_prefix(dir)?;
+    Ok(relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+fn restore_files(
+    directory: &str,
+    restore_map: &str,
+    verify: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     let json = fs::read_to_string(restore_map)?;
-    let mapping: HashMap<String, String> = serde_json::from_str(&json)?;
+    let mapping: BTreeMap<String, MappingEntry> = match serde_json::from_str(&json)? {
+        MappingFile::Versioned(mapping) => {
+            if mapping.version > MAPPING_VERSION {
+                return Err(format!(
+                    "Mapping file version {} is newer than supported version {}",
+                    mapping.version, MAPPING_VERSION
+                )
+                .into());
+            }
+            mapping.files
+        }
+        MappingFile::Legacy(files) => files
+            .into_iter()
+            .map(|(old_name, new_name)| (old_name, StoredEntry::Name(new_name).into()))
+            .collect(),
+    };
 
     let dir = Path::new(directory);
     let (restore_ops, missing): (Vec<_>, Vec<_>) = mapping
         .iter()
-        .map(|(old_name, new_name)| {
-            let new_path = dir.join(new_name);
+        .map(|(old_name, entry)| {
+            let new_path = dir.join(&entry.new_name);
             let old_path = dir.join(old_name);
 
             if new_path.exists() {
-                Ok((new_path, old_path))
+                Ok((new_path, old_path, entry))
             } else {
-                Err(new_name.to_string())
+                Err(entry.new_name.to_string())
             }
         })
         .partition(Result::is_ok);
@@ -101,6 +296,15 @@ fn restore_files(directory: &str, restore_map: &str) -> Result<(), Box<dyn std::
         }
     }
 
+    let restore_ops = if verify {
+        verify_restore_ops(restore_ops)?
+    } else {
+        restore_ops
+            .into_iter()
+            .map(|(new_path, old_path, _)| (new_path, old_path))
+            .collect()
+    };
+
     check_restore_conflicts(&restore_ops)?;
     perform_restore(&restore_ops)?;
     println!(
@@ -111,6 +315,59 @@ fn restore_files(directory: &str, restore_map: &str) -> Result<(), Box<dyn std::
     Ok(())
 }
 
+/// Keep only the files whose size and CRC32 still match the mapping, warning about the rest.
+/// Entries without a recorded checksum are restored unverified.
+fn verify_restore_ops(
+    restore_ops: Vec<(PathBuf, PathBuf, &MappingEntry)>,
+) -> Result<Vec<(PathBuf, PathBuf)>, Box<dyn std::error::Error>> {
+    let mut verified = Vec::new();
+    let mut mismatched = Vec::new();
+    let mut unchecked = 0;
+
+    for (new_path, old_path, entry) in restore_ops {
+        let (Some(size), Some(crc32)) = (entry.size, entry.crc32) else {
+            unchecked += 1;
+            verified.push((new_path, old_path));
+            continue;
+        };
+        // Cheap size check first, only hash files that could still match
+        if fs::metadata(&new_path)?.len() == size && file_crc32(&new_path)? == crc32 {
+            verified.push((new_path, old_path));
+        } else {
+            mismatched.push(new_path);
+        }
+    }
+
+    if unchecked > 0 {
+        eprintln!(
+            "Warning: {} entries have no recorded checksum and were not verified",
+            unchecked
+        );
+    }
+    if !mismatched.is_empty() {
+        eprintln!("Warning: Files changed since renaming, skipped:");
+        for path in &mismatched {
+            eprintln!("- {}", path.display());
+        }
+    }
+    Ok(verified)
+}
+
+/// CRC32 of the file contents.
+fn file_crc32(path: &Path) -> io::Result<u32> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize())
+}
+
 fn perform_restore(
     restore_ops: &[(std::path::PathBuf, std::path::PathBuf)],
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -127,55 +384,59 @@ fn perform_restore(
     Ok(())
 }
 
-fn check_conflicts(
-    rename_ops: &[(std::path::PathBuf, std::path::PathBuf, String)],
-) -> Result<(), Box<dyn std::error::Error>> {
+/// Return every planned destination that already exists.
+fn check_conflicts(rename_ops: &[RenameOp]) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
     let pb = ProgressBar::new(rename_ops.len() as u64);
     pb.set_style(ProgressStyle::default_bar()
         .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) Checking...")?
         .progress_chars("##-"));
 
-    for (_, new_path, _) in rename_ops {
+    let mut conflicts = Vec::new();
+    for op in rename_ops {
         pb.inc(1);
-        if new_path.exists() {
-            pb.finish_with_message("Conflict detected");
-            eprintln!("\nError: File already exists: {}", new_path.display());
-            std::process::exit(1);
+        // A file that already has its target name is not a conflict
+        if op.new_path != op.old_path && op.new_path.exists() {
+            conflicts.push(op.new_path.clone());
         }
     }
-    pb.finish_with_message("No conflicts found");
-    Ok(())
+    if conflicts.is_empty() {
+        pb.finish_with_message("No conflicts found");
+    } else {
+        pb.finish_with_message("Conflicts detected");
+    }
+    Ok(conflicts)
 }
 
-fn perform_renames(
-    rename_ops: &[(std::path::PathBuf, std::path::PathBuf, String)],
-) -> Result<(), Box<dyn std::error::Error>> {
+fn perform_renames(rename_ops: &[RenameOp]) -> Result<(), Box<dyn std::error::Error>> {
     let pb = ProgressBar::new(rename_ops.len() as u64);
     pb.set_style(ProgressStyle::default_bar()
         .template("{spinner:.yellow} [{elapsed_precise}] [{bar:40.yellow/green}] {pos}/{len} ({eta}) Renaming...")?
         .progress_chars("##-"));
 
-    for (old_path, new_path, _) in rename_ops {
+    for op in rename_ops {
         pb.inc(1);
-        fs::rename(old_path, new_path)?;
+        fs::rename(&op.old_path, &op.new_path)?;
     }
     pb.finish_with_message("Renaming completed");
     Ok(())
 }
 
-fn save_mapping(
-    rename_ops: &[(std::path::PathBuf, std::path::PathBuf, String)],
-    output: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mapping: HashMap<String, String> = rename_ops
+fn save_mapping(rename_ops: &[RenameOp], output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let files = rename_ops
         .iter()
-        .map(|(_, new_path, old_name)| {
-            (
-                old_name.clone(),
-                new_path.file_name().unwrap().to_str().unwrap().to_string(),
-            )
+        .map(|op| {
+            let entry = MappingEntry {
+                new_name: op.new_name.clone(),
+                size: Some(fs::metadata(&op.new_path)?.len()),
+                crc32: Some(file_crc32(&op.new_path)?),
+            };
+            Ok((op.old_name.clone(), entry))
         })
-        .collect();
+        .collect::<io::Result<_>>()?;
+    let mapping = Mapping {
+        version: MAPPING_VERSION,
+        files,
+    };
 
     let json = serde_json::to_string_pretty(&mapping)?;
     fs::write(output, json)?;
@@ -191,9 +452,9 @@ fn check_restore_conflicts(
         .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) Checking...")?
         .progress_chars("##-"));
 
-    for (_, old_path) in restore_ops {
+    for (new_path, old_path) in restore_ops {
         pb.inc(1);
-        if old_path.exists() {
+        if old_path != new_path && old_path.exists() {
             pb.finish_with_message("Conflict detected");
             eprintln!(
                 "\nError: Target file already exists: {}",
@@ -205,3 +466,38 @@ fn check_restore_conflicts(
     pb.finish_with_message("No conflicts found");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_padded_names_sort_in_order() {
+        let dir = std::env::temp_dir().join(format!("renm_pad_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let originals: Vec<String> = (b'a'..=b'l')
+            .map(|c| format!("{}.txt", c as char))
+            .collect();
+        for name in &originals {
+            fs::write(dir.join(name), name).unwrap();
+        }
+
+        let ops = plan_renames(&dir, false, false, Some(0)).unwrap();
+        perform_renames(&ops).unwrap();
+
+        let mut renamed: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        renamed.sort();
+        let expected: Vec<String> = (1..=12).map(|i| format!("{:02}.txt", i)).collect();
+        assert_eq!(renamed, expected);
+
+        // Lexical order of the new names follows the original order
+        for (original, new_name) in originals.iter().zip(&expected) {
+            assert_eq!(fs::read_to_string(dir.join(new_name)).unwrap(), *original);
+        }
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
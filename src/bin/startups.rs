@@ -1,5 +1,6 @@
 use clap::{Args, Parser, Subcommand};
-use mslnk::ShellLink;
+use serde::Serialize;
+use std::error::Error;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -7,7 +8,7 @@ use std::process::Command;
 #[derive(Parser)]
 #[command(name = "StartupManager")]
 #[command(version)]
-#[command(about = "Manage startup applications on Windows", long_about = None)]
+#[command(about = "Manage startup applications on Windows, Linux and macOS", long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -19,8 +20,12 @@ enum Commands {
     Add(AddArgs),
     /// Remove an application from startup
     Remove(RemoveArgs),
+    /// Disable a startup application without deleting its entry
+    Disable(ToggleArgs),
+    /// Re-enable a previously disabled startup application
+    Enable(ToggleArgs),
     /// List all startup applications
-    List,
+    List(ListArgs),
     /// Open the startup directory in the file explorer
     View,
 }
@@ -31,26 +36,580 @@ struct AddArgs {
     #[arg(required = true)]
     app_path: String,
 
-    /// The name of the shortcut (without .lnk extension)
+    /// The name of the startup entry (without file extension)
     #[arg(required = true)]
     name: String,
 
     /// The working directory for the application (defaults to the parent directory of the app)
     #[arg(short, long)]
     working_dir: Option<String>,
+
+    /// Command-line arguments passed to the application
+    #[arg(short, long, allow_hyphen_values = true)]
+    args: Option<String>,
+
+    /// Start the application with a minimized window (Windows only)
+    #[arg(long)]
+    minimized: bool,
+
+    /// Request elevation when the application starts (Windows only)
+    #[arg(long)]
+    admin: bool,
 }
 
 #[derive(Args)]
 struct RemoveArgs {
-    /// The name of the shortcut to remove (without .lnk extension)
+    /// The name of the startup entry to remove (without file extension)
     #[arg(required = true)]
     name: String,
 }
 
-fn get_startup_dir() -> PathBuf {
-    let mut startup_dir = dirs::data_dir().expect("Unable to find home directory");
-    startup_dir.push(r"Microsoft\Windows\Start Menu\Programs\Startup");
-    startup_dir
+#[derive(Args)]
+struct ToggleArgs {
+    /// The name of the startup entry (without file extension)
+    #[arg(required = true)]
+    name: String,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// Print the entries as JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+/// An application to launch when the user logs in
+struct StartupEntry {
+    name: String,
+    app_path: PathBuf,
+    working_dir: PathBuf,
+    arguments: Option<String>,
+    minimized: bool,
+    admin: bool,
+}
+
+/// A startup entry found on disk
+#[derive(Serialize)]
+struct ListedEntry {
+    name: String,
+    disabled: bool,
+    #[serde(flatten)]
+    details: EntryDetails,
+}
+
+/// What a startup entry actually runs
+#[derive(Serialize, Default)]
+struct EntryDetails {
+    target: String,
+    arguments: String,
+    working_dir: String,
+}
+
+/// Moves an entry file between its enabled and disabled locations
+fn move_entry(from: &Path, to: &Path) -> Result<bool, Box<dyn Error>> {
+    if !from.exists() {
+        return Ok(false);
+    }
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()).into());
+    }
+    fs::create_dir_all(to.parent().unwrap())?;
+    fs::rename(from, to)?;
+    Ok(true)
+}
+
+/// The platform mechanism that launches applications at login
+trait StartupBackend {
+    /// Directory scanned by the platform for startup entries
+    fn startup_dir(&self) -> PathBuf;
+
+    /// File extension of a startup entry, without the leading dot
+    fn extension(&self) -> &'static str;
+
+    /// Command opening a directory in the file manager
+    fn file_manager(&self) -> &'static str;
+
+    /// Writes the file describing `entry` to `path`
+    fn write_entry(&self, entry: &StartupEntry, path: &Path) -> Result<(), Box<dyn Error>>;
+
+    /// Reads the target, arguments and working directory back from the entry file at `path`
+    fn describe(&self, path: &Path) -> Result<EntryDetails, Box<dyn Error>>;
+
+    /// Whether entries honor the window state and elevation flags
+    fn supports_window_flags(&self) -> bool {
+        false
+    }
+
+    /// Directory holding disabled entries, by default a sibling of the startup directory
+    /// which the platform ignores
+    fn disabled_dir(&self) -> PathBuf {
+        let dir = self.startup_dir();
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+        dir.with_file_name(format!("{}.disabled", name))
+    }
+
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.startup_dir()
+            .join(format!("{}.{}", name, self.extension()))
+    }
+
+    /// Where the entry is kept while disabled
+    fn disabled_entry_path(&self, name: &str) -> PathBuf {
+        self.disabled_dir()
+            .join(format!("{}.{}", name, self.extension()))
+    }
+
+    fn add(&self, entry: &StartupEntry) -> Result<PathBuf, Box<dyn Error>> {
+        fs::create_dir_all(self.startup_dir())?;
+        let path = self.entry_path(&entry.name);
+        self.write_entry(entry, &path)?;
+        Ok(path)
+    }
+
+    /// Removes the entry, enabled or disabled, returning whether it existed
+    fn remove(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        let mut removed = false;
+        for path in [self.entry_path(name), self.disabled_entry_path(name)] {
+            if path.exists() {
+                fs::remove_file(path)?;
+                removed = true;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Moves the entry to its disabled location, returning whether it was enabled
+    fn disable(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        move_entry(&self.entry_path(name), &self.disabled_entry_path(name))
+    }
+
+    /// Moves the entry back into the startup directory, returning whether it was disabled
+    fn enable(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        move_entry(&self.disabled_entry_path(name), &self.entry_path(name))
+    }
+
+    /// Entries of the startup and disabled directories, sorted by name
+    fn list(&self) -> Result<Vec<ListedEntry>, Box<dyn Error>> {
+        let mut dirs = vec![self.startup_dir()];
+        if self.disabled_dir() != dirs[0] {
+            dirs.push(self.disabled_dir());
+        }
+        let enabled_suffix = format!(".{}", self.extension());
+        let disabled_suffix = format!("{}.disabled", enabled_suffix);
+
+        let mut entries = Vec::new();
+        for dir in dirs {
+            if !dir.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                let Some(name) = file_name
+                    .strip_suffix(&disabled_suffix)
+                    .or_else(|| file_name.strip_suffix(&enabled_suffix))
+                else {
+                    continue;
+                };
+                let disabled = if path == self.entry_path(name) {
+                    false
+                } else if path == self.disabled_entry_path(name) {
+                    true
+                } else {
+                    continue;
+                };
+                let details = self.describe(&path).unwrap_or_else(|e| {
+                    eprintln!("Failed to read {}: {}", path.display(), e);
+                    EntryDetails::default()
+                });
+                entries.push(ListedEntry {
+                    name: name.to_string(),
+                    disabled,
+                    details,
+                });
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+}
+
+/// `.lnk` shortcuts in the Startup folder of the start menu
+#[cfg(windows)]
+struct WindowsStartup;
+
+#[cfg(windows)]
+impl StartupBackend for WindowsStartup {
+    fn startup_dir(&self) -> PathBuf {
+        let mut startup_dir = dirs::data_dir().expect("Unable to find home directory");
+        startup_dir.push(r"Microsoft\Windows\Start Menu\Programs\Startup");
+        startup_dir
+    }
+
+    fn extension(&self) -> &'static str {
+        "lnk"
+    }
+
+    fn file_manager(&self) -> &'static str {
+        "explorer"
+    }
+
+    fn write_entry(&self, entry: &StartupEntry, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut link = mslnk::ShellLink::new(&entry.app_path)
+            .map_err(|e| format!("Failed to create shortcut: {}", e))?;
+        link.set_working_dir(Some(entry.working_dir.to_string_lossy().to_string()));
+        link.set_arguments(entry.arguments.clone());
+        if entry.minimized {
+            link.header_mut()
+                .set_show_command(mslnk::ShowCommand::ShowMinNoActive);
+        }
+        if entry.admin {
+            link.header_mut()
+                .update_link_flags(mslnk::LinkFlags::RUN_AS_USER, true);
+        }
+        link.create_lnk(path)
+            .map_err(|e| format!("Failed to save shortcut: {}", e))?;
+        Ok(())
+    }
+
+    fn describe(&self, path: &Path) -> Result<EntryDetails, Box<dyn Error>> {
+        let link = lnk::ShellLink::open(path, lnk::encoding::WINDOWS_1252)
+            .map_err(|e| format!("Failed to parse shortcut: {}", e))?;
+        let strings = link.string_data();
+        Ok(EntryDetails {
+            target: link
+                .link_target()
+                .or_else(|| strings.relative_path().clone())
+                .unwrap_or_default(),
+            arguments: strings.command_line_arguments().clone().unwrap_or_default(),
+            working_dir: strings.working_dir().clone().unwrap_or_default(),
+        })
+    }
+
+    fn supports_window_flags(&self) -> bool {
+        true
+    }
+
+    /// Disabled shortcuts stay in the Startup folder, since any sibling folder would
+    /// show up as a program group in the start menu
+    fn disabled_dir(&self) -> PathBuf {
+        self.startup_dir()
+    }
+
+    /// `<name>.lnk.disabled`, which Windows does not launch at login
+    fn disabled_entry_path(&self, name: &str) -> PathBuf {
+        self.startup_dir()
+            .join(format!("{}.{}.disabled", name, self.extension()))
+    }
+}
+
+/// XDG autostart `.desktop` files, honored by most Linux desktop environments
+#[cfg(all(unix, not(target_os = "macos")))]
+struct XdgAutostart;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl StartupBackend for XdgAutostart {
+    fn startup_dir(&self) -> PathBuf {
+        dirs::config_dir()
+            .expect("Unable to find config directory")
+            .join("autostart")
+    }
+
+    fn extension(&self) -> &'static str {
+        "desktop"
+    }
+
+    fn file_manager(&self) -> &'static str {
+        "xdg-open"
+    }
+
+    fn write_entry(&self, entry: &StartupEntry, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\nPath={}\nX-GNOME-Autostart-enabled=true\n",
+            entry.name,
+            exec_line(entry),
+            entry.working_dir.display()
+        );
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn describe(&self, path: &Path) -> Result<EntryDetails, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let mut details = EntryDetails::default();
+        let mut in_entry_group = false;
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_entry_group = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_entry_group {
+                continue;
+            }
+            if let Some(exec) = line.strip_prefix("Exec=") {
+                (details.target, details.arguments) = split_exec_program(exec);
+            } else if let Some(dir) = line.strip_prefix("Path=") {
+                details.working_dir = dir.to_string();
+            }
+        }
+        Ok(details)
+    }
+}
+
+/// Builds the `Exec` value: the quoted program followed by the arguments as given
+#[cfg(all(unix, not(target_os = "macos")))]
+fn exec_line(entry: &StartupEntry) -> String {
+    let program = quote_exec_arg(&entry.app_path.to_string_lossy());
+    match entry.arguments.as_deref().map(str::trim) {
+        Some(arguments) if !arguments.is_empty() => format!("{} {}", program, arguments),
+        _ => program,
+    }
+}
+
+/// Splits a desktop entry `Exec` value into the unquoted program and its remaining arguments
+#[cfg(all(unix, not(target_os = "macos")))]
+fn split_exec_program(exec: &str) -> (String, String) {
+    let exec = exec.trim_start();
+    let Some(quoted) = exec.strip_prefix('"') else {
+        return match exec.split_once(char::is_whitespace) {
+            Some((program, arguments)) => (program.to_string(), arguments.trim().to_string()),
+            None => (exec.to_string(), String::new()),
+        };
+    };
+
+    let mut program = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => program.extend(chars.next().map(|(_, escaped)| escaped)),
+            '"' => return (program, quoted[index + 1..].trim().to_string()),
+            _ => program.push(c),
+        }
+    }
+    (program, String::new())
+}
+
+/// Quotes an argument of a desktop entry `Exec` key when it contains reserved characters
+#[cfg(all(unix, not(target_os = "macos")))]
+fn quote_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')', '`',
+    ];
+    if !arg.contains(RESERVED) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// launchd agents in `~/Library/LaunchAgents`, loaded at login
+#[cfg(target_os = "macos")]
+struct LaunchAgents;
+
+#[cfg(target_os = "macos")]
+impl StartupBackend for LaunchAgents {
+    fn startup_dir(&self) -> PathBuf {
+        dirs::home_dir()
+            .expect("Unable to find home directory")
+            .join("Library/LaunchAgents")
+    }
+
+    fn extension(&self) -> &'static str {
+        "plist"
+    }
+
+    fn file_manager(&self) -> &'static str {
+        "open"
+    }
+
+    fn write_entry(&self, entry: &StartupEntry, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+{}
+    </array>
+    <key>WorkingDirectory</key>
+    <string>{}</string>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+            escape_xml(&entry.name),
+            program_arguments(entry),
+            escape_xml(&entry.working_dir.to_string_lossy())
+        );
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn describe(&self, path: &Path) -> Result<EntryDetails, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let re_arguments =
+            regex::Regex::new(r"(?s)<key>ProgramArguments</key>\s*<array>(.*?)</array>")?;
+        let re_working_dir =
+            regex::Regex::new(r"(?s)<key>WorkingDirectory</key>\s*<string>(.*?)</string>")?;
+        let re_string = regex::Regex::new(r"(?s)<string>(.*?)</string>")?;
+
+        let program_arguments: Vec<String> = re_arguments
+            .captures(&content)
+            .map(|caps| {
+                re_string
+                    .captures_iter(&caps[1])
+                    .map(|arg| unescape_xml(&arg[1]))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(EntryDetails {
+            target: program_arguments.first().cloned().unwrap_or_default(),
+            arguments: program_arguments.get(1..).unwrap_or_default().join(" "),
+            working_dir: re_working_dir
+                .captures(&content)
+                .map(|caps| unescape_xml(&caps[1]))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// The `<string>` elements of `ProgramArguments`: the program, then each argument
+#[cfg(target_os = "macos")]
+fn program_arguments(entry: &StartupEntry) -> String {
+    let program = entry.app_path.to_string_lossy().to_string();
+    let arguments = split_arguments(entry.arguments.as_deref().unwrap_or_default());
+    std::iter::once(program)
+        .chain(arguments)
+        .map(|arg| format!("        <string>{}</string>", escape_xml(&arg)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Splits a command line on whitespace, keeping single- or double-quoted parts together
+#[cfg(target_os = "macos")]
+fn split_arguments(command_line: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut in_argument = false;
+    let mut quote = None;
+
+    for c in command_line.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_argument = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_argument {
+                    arguments.push(std::mem::take(&mut current));
+                    in_argument = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_argument = true;
+            }
+        }
+    }
+    if in_argument {
+        arguments.push(current);
+    }
+    arguments
+}
+
+#[cfg(target_os = "macos")]
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(target_os = "macos")]
+fn unescape_xml(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&gt;", ">")
+        .replace("&lt;", "<")
+        .replace("&amp;", "&")
+}
+
+/// The startup backend of the platform this binary was built for
+#[cfg(windows)]
+fn platform_backend() -> Box<dyn StartupBackend> {
+    Box::new(WindowsStartup)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_backend() -> Box<dyn StartupBackend> {
+    Box::new(LaunchAgents)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_backend() -> Box<dyn StartupBackend> {
+    Box::new(XdgAutostart)
+}
+
+/// Prints the entries as a table whose columns fit their widest value
+fn print_entries(entries: &[ListedEntry]) {
+    let headers = [
+        "Startup Entry",
+        "Disabled",
+        "Target",
+        "Arguments",
+        "Working Directory",
+    ];
+    let rows: Vec<[&str; 5]> = entries
+        .iter()
+        .map(|entry| {
+            [
+                entry.name.as_str(),
+                if entry.disabled { "yes" } else { "no" },
+                entry.details.target.as_str(),
+                entry.details.arguments.as_str(),
+                entry.details.working_dir.as_str(),
+            ]
+        })
+        .collect();
+
+    let mut widths = headers.map(|header| header.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: &[&str; 5]| {
+        cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", format_row(&headers));
+    println!(
+        "{:-<1$}",
+        "",
+        widths.iter().sum::<usize>() + 2 * (widths.len() - 1)
+    ); // Separator line
+    for row in &rows {
+        println!("{}", format_row(row));
+    }
 }
 
 fn capitalize_first_letter(s: &str) -> String {
@@ -74,71 +633,92 @@ fn convert_to_absolute(path: &str) -> PathBuf {
 
 fn main() {
     let cli = Cli::parse();
-    let startup_dir = get_startup_dir();
+    let backend = platform_backend();
+    let startup_dir = backend.startup_dir();
 
     match &cli.command {
         Commands::Add(args) => {
             // Convert app_path to absolute path if it's relative
             let abs_app_path = convert_to_absolute(&args.app_path);
 
-            // Let name's first letter be capitalized
-            let name = capitalize_first_letter(&args.name);
-            let lnk_path = startup_dir.join(format!("{}.lnk", name));
-
-            let mut link = ShellLink::new(abs_app_path.to_str().unwrap()).unwrap_or_else(|e| {
-                eprintln!("Failed to create shortcut: {}", e);
-                std::process::exit(1);
-            });
-
             // Set working directory
             let working_dir = if let Some(ref dir) = args.working_dir {
-                convert_to_absolute(dir).to_string_lossy().to_string()
+                convert_to_absolute(dir)
             } else {
                 // Default to use the app's directory
                 abs_app_path
                     .parent()
                     .expect("Failed to get parent directory")
-                    .to_string_lossy()
-                    .to_string()
+                    .to_path_buf()
             };
 
-            link.set_working_dir(Some(working_dir));
+            let entry = StartupEntry {
+                // Let name's first letter be capitalized
+                name: capitalize_first_letter(&args.name),
+                app_path: abs_app_path,
+                working_dir,
+                arguments: args.args.clone(),
+                minimized: args.minimized,
+                admin: args.admin,
+            };
 
-            link.create_lnk(lnk_path.to_str().unwrap())
-                .unwrap_or_else(|e| {
-                    eprintln!("Failed to save shortcut: {}", e);
-                    std::process::exit(1);
-                });
+            if (entry.minimized || entry.admin) && !backend.supports_window_flags() {
+                eprintln!("--minimized and --admin only apply to Windows shortcuts, ignoring them");
+            }
 
-            println!("Added {} as {}", abs_app_path.display(), lnk_path.display());
+            let entry_path = backend.add(&entry).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            });
+
+            println!(
+                "Added {} as {}",
+                entry.app_path.display(),
+                entry_path.display()
+            );
         }
-        Commands::Remove(args) => {
-            let lnk_path = startup_dir.join(format!("{}.lnk", args.name));
-            if lnk_path.exists() {
-                fs::remove_file(lnk_path).expect("Failed to remove the shortcut");
-                println!("Removed shortcut for {}", args.name);
-            } else {
-                println!("Shortcut for {} does not exist.", args.name);
+        Commands::Remove(args) => match backend.remove(&args.name) {
+            Ok(true) => println!("Removed startup entry for {}", args.name),
+            Ok(false) => println!("Startup entry for {} does not exist.", args.name),
+            Err(e) => {
+                eprintln!("Failed to remove the startup entry: {}", e);
+                std::process::exit(1);
             }
-        }
-        Commands::List => {
-            println!("{:<30}", "Shortcut Name");
-            println!("{:-<30}", ""); // Separator line
-
-            for entry in fs::read_dir(startup_dir).expect("Failed to read startup directory") {
-                let entry = entry.expect("Failed to list contents");
-                let path = entry.path();
-
-                // Only handle .lnk files
-                if path.extension().and_then(|ext| ext.to_str()) == Some("lnk") {
-                    let file_name = path.file_stem().unwrap().to_string_lossy().to_string();
-                    println!("{:<30}", file_name);
-                }
+        },
+        Commands::Disable(args) => match backend.disable(&args.name) {
+            Ok(true) => println!("Disabled startup entry for {}", args.name),
+            Ok(false) => println!("No enabled startup entry for {}.", args.name),
+            Err(e) => {
+                eprintln!("Failed to disable the startup entry: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::Enable(args) => match backend.enable(&args.name) {
+            Ok(true) => println!("Enabled startup entry for {}", args.name),
+            Ok(false) => println!("No disabled startup entry for {}.", args.name),
+            Err(e) => {
+                eprintln!("Failed to enable the startup entry: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::List(args) => {
+            let entries = backend.list().unwrap_or_else(|e| {
+                eprintln!("Failed to read startup directory: {}", e);
+                std::process::exit(1);
+            });
+
+            if args.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&entries).expect("Failed to serialize entries")
+                );
+                return;
             }
+            print_entries(&entries);
         }
         Commands::View => {
             // Use system command to open startup directory
-            Command::new("explorer")
+            Command::new(backend.file_manager())
                 .arg(startup_dir.to_str().unwrap())
                 .output()
                 .expect("Failed to open startup directory");
@@ -1,5 +1,7 @@
 use clap::Parser;
 use dialoguer::{theme::ColorfulTheme, Input, Select};
+use serde::Deserialize;
+use strsim::normalized_levenshtein;
 // Added Input
 use std::{
     fmt, fs, io,
@@ -15,6 +17,8 @@ enum AppError {
     // Removed: Regex(regex::Error),
     /// Error during user interaction (e.g., dialoguer).
     Interaction(dialoguer::Error),
+    /// Error reading the manifest CSV.
+    Csv(csv::Error),
     // Removed: StudentInfoExtraction(String),
     /// No files found in the specified directory.
     NoFilesFound(PathBuf),
@@ -28,6 +32,7 @@ impl fmt::Display for AppError {
             AppError::Io(err) => write!(f, "IO error: {}", err),
             // Removed: AppError::Regex(err) => write!(f, "Regex error: {}", err),
             AppError::Interaction(err) => write!(f, "Interaction error: {}", err),
+            AppError::Csv(err) => write!(f, "CSV error: {}", err),
             // Removed: AppError::StudentInfoExtraction(path_str)
             AppError::NoFilesFound(dir) => {
                 write!(f, "No files found in directory: {}", dir.display())
@@ -43,6 +48,7 @@ impl std::error::Error for AppError {
             AppError::Io(err) => Some(err),
             // Removed: AppError::Regex(err) => Some(err),
             AppError::Interaction(err) => Some(err),
+            AppError::Csv(err) => Some(err),
             _ => None,
         }
     }
@@ -62,6 +68,12 @@ impl From<dialoguer::Error> for AppError {
     }
 }
 
+impl From<csv::Error> for AppError {
+    fn from(err: csv::Error) -> Self {
+        AppError::Csv(err)
+    }
+}
+
 /// Command-line arguments for the file renaming utility.
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Renames student project files based on a predefined structure.", long_about = None)]
@@ -74,6 +86,27 @@ struct Args {
     #[arg(short, long)]
     output_dir: Option<PathBuf>,
     // Removed: student_info_regex field
+    /// Document types to leave unassigned without prompting (comma-separated).
+    #[arg(short, long, value_delimiter = ',')]
+    skip: Vec<String>,
+
+    /// CSV with a `student_id,student_name,doc_type,source_filename` header. Every row is
+    /// renamed without prompting; `source_filename` is relative to the input directory.
+    #[arg(short, long)]
+    manifest: Option<PathBuf>,
+
+    /// Move the files instead of copying them.
+    #[arg(long = "move")]
+    move_files: bool,
+}
+
+/// One row of the manifest CSV.
+#[derive(Deserialize, Debug)]
+struct ManifestRow {
+    student_id: String,
+    student_name: String,
+    doc_type: String,
+    source_filename: String,
 }
 
 /// Defines the standard document types and their processing order.
@@ -82,7 +115,7 @@ const DOC_TYPES: &[&str] = &[
     "文献综述",
     "外文翻译",
     "开题报告",
-    "skip", // Special type, can be used to skip a step or assign a generic file
+    SKIP_DOC_TYPE, // Placeholder that only reserves an index, never prompted for
     "教师中期检查表",
     "毕业设计过程稿",
     "毕业设计过程稿图纸",
@@ -93,12 +126,25 @@ const DOC_TYPES: &[&str] = &[
     "评阅教师评阅",
 ];
 
+/// Placeholder entry in `DOC_TYPES`.
+const SKIP_DOC_TYPE: &str = "skip";
+
+/// Selection list entry that leaves the current document type unassigned.
+const SKIP_SELECTION: &str = "(skip this document)";
+
 /// Main application logic.
 fn main() -> Result<(), AppError> {
     let args = Args::parse();
     let input_dir = args.dir.unwrap_or_else(|| PathBuf::from("."));
     let output_dir = args.output_dir.unwrap_or_else(|| input_dir.clone());
 
+    if let Some(unknown) = args.skip.iter().find(|t| !DOC_TYPES.contains(&t.as_str())) {
+        return Err(AppError::Processing(format!(
+            "Unknown document type to skip: {}",
+            unknown
+        )));
+    }
+
     // Ensure output directory exists
     if !output_dir.exists() {
         fs::create_dir_all(&output_dir)?;
@@ -107,13 +153,17 @@ fn main() -> Result<(), AppError> {
     println!("Processing directory: {}", input_dir.display());
     println!("Output directory: {}", output_dir.display());
 
+    if let Some(manifest) = &args.manifest {
+        return process_manifest(manifest, &input_dir, &output_dir, args.move_files);
+    }
+
     let files = list_files_in_directory(&input_dir)?;
     if files.is_empty() {
         return Err(AppError::NoFilesFound(input_dir));
     }
 
     // Prepare a list of references to all files for selection prompts
-    let all_file_entries_for_selection: Vec<&fs::DirEntry> = files.iter().collect();
+    let mut all_file_entries_for_selection: Vec<&fs::DirEntry> = files.iter().collect();
 
     let theme = ColorfulTheme::default();
 
@@ -133,10 +183,27 @@ fn main() -> Result<(), AppError> {
     );
 
     for (index, &doc_type) in DOC_TYPES.iter().enumerate() {
+        // The placeholder keeps its index so later document types keep their numbers
+        if doc_type == SKIP_DOC_TYPE {
+            continue;
+        }
+        if args.skip.iter().any(|t| t == doc_type) {
+            println!("\nSkipping document type: {}", doc_type);
+            continue;
+        }
         println!("\nProcessing document type: {}", doc_type);
+        if all_file_entries_for_selection.is_empty() {
+            println!("No files left to assign for: {}", doc_type);
+            continue;
+        }
 
         // User selects from all files in the directory for the current document type
-        let selected_entry = prompt_for_file_selection(&all_file_entries_for_selection, doc_type)?;
+        let Some(selected_entry) =
+            prompt_for_file_selection(&all_file_entries_for_selection, doc_type)?
+        else {
+            println!("No file assigned for: {}", doc_type);
+            continue;
+        };
         let selected_path = selected_entry.path();
 
         let new_filename = construct_new_filename(
@@ -149,14 +216,79 @@ fn main() -> Result<(), AppError> {
 
         let destination_path = output_dir.join(&new_filename);
 
-        copy_file_to_output(&selected_path, &destination_path)?;
-        println!("Copied and renamed to: {}", destination_path.display());
+        transfer_file_to_output(&selected_path, &destination_path, args.move_files)?;
+
+        // A moved file is gone from the input directory, so stop offering it
+        if args.move_files {
+            all_file_entries_for_selection.retain(|entry| entry.path() != selected_path);
+        }
     }
 
     println!("\nProcessing complete.");
     Ok(())
 }
 
+/// Renames every file listed in the manifest without prompting.
+///
+/// # Arguments
+/// * `manifest` - The manifest CSV path.
+/// * `input_dir` - The directory `source_filename` is resolved against.
+/// * `output_dir` - The directory the renamed files are written to.
+/// * `move_files` - Whether to move the files instead of copying them.
+///
+/// # Returns
+/// An `AppError` if a row is invalid or a file operation fails.
+fn process_manifest(
+    manifest: &Path,
+    input_dir: &Path,
+    output_dir: &Path,
+    move_files: bool,
+) -> Result<(), AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(manifest)?;
+
+    let mut count = 0;
+    for (line, row) in reader.deserialize::<ManifestRow>().enumerate() {
+        let row = row?;
+        let index = DOC_TYPES
+            .iter()
+            .position(|&t| t != SKIP_DOC_TYPE && t == row.doc_type)
+            .ok_or_else(|| {
+                AppError::Processing(format!(
+                    "Unknown document type \"{}\" on manifest row {}",
+                    row.doc_type,
+                    line + 1
+                ))
+            })?;
+
+        let source_path = input_dir.join(&row.source_filename);
+        if !source_path.is_file() {
+            return Err(AppError::Processing(format!(
+                "Source file {} on manifest row {} does not exist",
+                source_path.display(),
+                line + 1
+            )));
+        }
+
+        let new_filename = construct_new_filename(
+            index + 1,
+            &row.student_id,
+            &row.student_name,
+            &row.doc_type,
+            &source_path,
+        )?;
+
+        let destination_path = output_dir.join(&new_filename);
+
+        transfer_file_to_output(&source_path, &destination_path, move_files)?;
+        count += 1;
+    }
+
+    println!("\nProcessing complete. {} files renamed.", count);
+    Ok(())
+}
+
 /// Lists all files in the specified directory.
 ///
 /// # Arguments
@@ -173,29 +305,57 @@ fn list_files_in_directory(dir: &Path) -> Result<Vec<fs::DirEntry>, AppError> {
 
 // Removed: extract_student_info_from_filename function
 
+/// Scores how likely `file_name` is the file for `doc_type`.
+///
+/// File names containing the document type rank above all others; within each group the
+/// normalized Levenshtein similarity of the file stem breaks the tie, so "毕业设计定稿.docx"
+/// outranks "毕业设计定稿图纸.dwg" for "毕业设计定稿".
+///
+/// # Arguments
+/// * `doc_type` - The document type keyword.
+/// * `file_name` - The candidate file name.
+///
+/// # Returns
+/// A score where higher means a better match.
+fn match_score(doc_type: &str, file_name: &str) -> f64 {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let similarity = normalized_levenshtein(doc_type, &stem);
+    if stem.contains(doc_type) {
+        1.0 + similarity
+    } else {
+        similarity
+    }
+}
+
 /// Prompts the user to select a file from a list of candidates.
 ///
+/// Candidates are sorted by `match_score`, so the most likely file is the default.
+///
 /// # Arguments
 /// * `candidates` - A slice of `fs::DirEntry` references representing candidate files.
 /// * `doc_type` - The document type for which the selection is being made (for the prompt message).
 ///
 /// # Returns
-/// A `Result` containing a reference to the selected `fs::DirEntry` or an `AppError`.
+/// A `Result` containing a reference to the selected `fs::DirEntry`, `None` if the user
+/// chose to skip this document, or an `AppError`.
 fn prompt_for_file_selection<'a>(
     candidates: &[&'a fs::DirEntry],
     doc_type: &str,
-) -> Result<&'a fs::DirEntry, AppError> {
+) -> Result<Option<&'a fs::DirEntry>, AppError> {
     let theme = ColorfulTheme::default();
-    let items: Vec<String> = candidates
+    let file_name = |e: &fs::DirEntry| e.file_name().to_string_lossy().into_owned();
+
+    // Stable sort keeps the directory order among equally scored files
+    let mut ranked: Vec<(&'a fs::DirEntry, f64)> = candidates
         .iter()
-        .map(|e| {
-            e.path()
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .into_owned()
-        })
+        .map(|&e| (e, match_score(doc_type, &file_name(e))))
         .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut items: Vec<String> = ranked.iter().map(|(e, _)| file_name(e)).collect();
 
     if items.is_empty() {
         // This case should ideally be prevented by the check in main() after list_files_in_directory
@@ -205,13 +365,15 @@ fn prompt_for_file_selection<'a>(
         )));
     }
 
+    items.push(SKIP_SELECTION.to_string());
+
     let selection_index = Select::with_theme(&theme)
         .with_prompt(format!("Select file for \"{}\"", doc_type))
         .items(&items)
         .default(0)
         .interact()?;
 
-    Ok(candidates[selection_index])
+    Ok(ranked.get(selection_index).map(|(e, _)| *e))
 }
 
 /// Constructs the new filename based on the predefined format.
@@ -250,15 +412,53 @@ fn construct_new_filename(
     }
 }
 
-/// Copies a file to the specified destination path.
+/// Copies or moves a file to the specified destination path.
+///
+/// If the destination already exists, a numeric suffix is appended to the file stem
+/// (`name_1.ext`, `name_2.ext`, ...) instead of overwriting it.
 ///
 /// # Arguments
-/// * `source_path` - The path of the file to copy.
-/// * `destination_path` - The path where the file should be copied to.
+/// * `source_path` - The path of the file to copy or move.
+/// * `destination_path` - The path where the file should be written to.
+/// * `move_file` - Whether to move the file instead of copying it.
 ///
 /// # Returns
-/// An `AppError` if the copy operation fails.
-fn copy_file_to_output(source_path: &PathBuf, destination_path: &PathBuf) -> Result<(), AppError> {
-    fs::copy(source_path, destination_path)?;
-    Ok(())
+/// A `Result` containing the path the file was actually written to or an `AppError`.
+fn transfer_file_to_output(
+    source_path: &Path,
+    destination_path: &Path,
+    move_file: bool,
+) -> Result<PathBuf, AppError> {
+    let final_path = unique_destination(destination_path);
+
+    if move_file {
+        // rename fails across filesystems, fall back to copy + remove
+        if fs::rename(source_path, &final_path).is_err() {
+            fs::copy(source_path, &final_path)?;
+            fs::remove_file(source_path)?;
+        }
+        println!("Moved and renamed to: {}", final_path.display());
+    } else {
+        fs::copy(source_path, &final_path)?;
+        println!("Copied and renamed to: {}", final_path.display());
+    }
+    Ok(final_path)
+}
+
+/// Returns `path` if it is free, otherwise the first `stem_N.ext` next to it that is.
+fn unique_destination(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map(|e| e.to_string_lossy());
+    let mut candidate = path.to_path_buf();
+    let mut counter = 1;
+
+    while candidate.exists() {
+        let name = match &extension {
+            Some(ext) => format!("{}_{}.{}", stem, counter, ext),
+            None => format!("{}_{}", stem, counter),
+        };
+        candidate = path.with_file_name(name);
+        counter += 1;
+    }
+    candidate
 }
@@ -1,5 +1,6 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
 use std::fs::{copy, create_dir_all, remove_dir_all};
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -12,6 +13,10 @@ struct Cli {
     #[arg(short, long, env = "GARBRO_ROOT")]
     bin_path: PathBuf,
 
+    /// Maximum number of GARbro processes to run at once.
+    #[arg(short, long, default_value_t = num_cpus::get(), value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    jobs: usize,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -25,7 +30,7 @@ enum Commands {
     },
     /// Perform an image conversion operation.
     Ic,
-    /// Convert all files with a specified extension in the given root paths to PNG images.
+    /// Convert all files with a specified extension in the given root paths to images (PNG by default).
     All {
         /// List of root paths to search for files.
         root_paths: Vec<PathBuf>,
@@ -38,8 +43,14 @@ enum Commands {
         /// Enable verbose output.
         #[arg(short, long, action)]
         verbose: bool,
+        /// Keep the temporary directory with the extracted files after a successful run.
+        #[arg(short, long, action)]
+        keep_temp: bool,
+        /// Image format to convert to.
+        #[arg(short, long, value_enum, default_value_t = TargetFormat::Png)]
+        target_format: TargetFormat,
     },
-    /// Convert files in the given root paths to PNG images, using a top-level approach.
+    /// Convert files in the given root paths to images (PNG by default), using a top-level approach.
     Top {
         /// List of root paths to search for files.
         root_paths: Vec<PathBuf>,
@@ -49,13 +60,52 @@ enum Commands {
         /// Enable verbose output.
         #[arg(short, long, action)]
         verbose: bool,
+        /// Image format to convert to.
+        #[arg(short, long, value_enum, default_value_t = TargetFormat::Png)]
+        target_format: TargetFormat,
     },
 }
 
+/// Output formats supported by GARbro's Image.Convert.
+#[derive(Clone, Copy, ValueEnum)]
+enum TargetFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    Tga,
+}
+
+impl TargetFormat {
+    /// Name passed to `Image.Convert.exe -t`.
+    fn garbro_name(self) -> &'static str {
+        match self {
+            TargetFormat::Png => "PNG",
+            TargetFormat::Jpeg => "JPEG",
+            TargetFormat::Bmp => "BMP",
+            TargetFormat::Tga => "TGA",
+        }
+    }
+
+    /// Whether a file with this extension needs no conversion. Common web formats are
+    /// always kept as they are, plus whatever is already in the target format.
+    fn is_converted(self, ext: &str) -> bool {
+        let ext = ext.to_ascii_lowercase();
+        let target: &[&str] = match self {
+            TargetFormat::Png => &["png"],
+            TargetFormat::Jpeg => &["jpg", "jpeg"],
+            TargetFormat::Bmp => &["bmp"],
+            TargetFormat::Tga => &["tga"],
+        };
+        matches!(ext.as_str(), "jpg" | "png" | "jpeg") || target.contains(&ext.as_str())
+    }
+}
+
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    rayon::ThreadPoolBuilder::new().num_threads(cli.jobs).build_global()?;
     let config = CommandConfig::new(cli.bin_path);
+    config.validate()?;
 
     match &cli.command {
         Commands::Ui { file } => exct_ui(&config, file)?,
@@ -65,12 +115,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             extension,
             output_dir,
             verbose,
-        } => exct_all(&config, root_paths, extension, output_dir, *verbose)?,
+            keep_temp,
+            target_format,
+        } => exct_all(&config, root_paths, extension, output_dir, *verbose, *keep_temp, *target_format)?,
         Commands::Top {
             root_paths,
             output_dir,
             verbose,
-        } => to_png(&config, root_paths, output_dir, *verbose)?,
+            target_format,
+        } => to_png(&config, root_paths, output_dir, *verbose, *target_format)?,
     }
 
     Ok(())
@@ -102,6 +155,24 @@ impl CommandConfig {
     fn csl_cmd_full(&self) -> String {
         self.barbro_root.join(self.csl_cmd).to_string_lossy().to_string()
     }
+
+    /// Check that all GARbro executables exist under the configured root.
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let missing: Vec<String> = [self.img_cmd_full(), self.gui_cmd_full(), self.csl_cmd_full()]
+            .into_iter()
+            .filter(|cmd| !Path::new(cmd).is_file())
+            .collect();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "GARbro executable(s) not found: {}. Pass --bin-path or set GARBRO_ROOT to the GARbro installation directory (currently {}).",
+            missing.join(", "),
+            self.barbro_root.display()
+        )
+        .into())
+    }
 }
 
 impl Default for CommandConfig {
@@ -133,14 +204,29 @@ fn exct_all(
     extension: &str,
     output_dir: &Path,
     verbose: bool,
+    keep_temp: bool,
+    target_format: TargetFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
     create_dir_all(output_dir)?;
-    let temp_dir = output_dir.join("temp");
+    // Unique per run so concurrent runs sharing an output directory don't mix their files
+    let temp_dir = output_dir.join(format!(
+        "temp_{}_{}",
+        chrono::Local::now().format("%Y%m%d%H%M%S"),
+        std::process::id()
+    ));
     create_dir_all(&temp_dir)?;
 
     _un_comp(config, root_paths, extension, &temp_dir, verbose)?;
-    _png_conv(config, &temp_dir, output_dir, verbose)?;
-    remove_dir_all(&temp_dir)?;
+    if let Err(e) = _png_conv(config, &temp_dir, output_dir, verbose, target_format) {
+        eprintln!("Conversion failed, extracted files are kept in {}", temp_dir.display());
+        return Err(e);
+    }
+
+    if keep_temp {
+        println!("Extracted files are kept in {}", temp_dir.display());
+    } else {
+        remove_dir_all(&temp_dir)?;
+    }
 
     Ok(())
 }
@@ -174,23 +260,25 @@ fn _un_comp(
         .unwrap()
         .progress_chars("#>-"));
 
-    for f in compressed_files {
+    compressed_files.into_par_iter().try_for_each(|f| {
         let output = Command::new(config.csl_cmd_full())
             .args(["-x", f.to_str().unwrap()])
             .current_dir(temp_dir)
-            .output()?;
+            .output()
+            .map_err(|e| format!("Failed to uncompress {}: {}", f.display(), e))?;
 
         if !output.status.success() {
-            eprintln!("{}", String::from_utf8_lossy(&output.stderr));
-            return Err(format!("Failed to uncompress {}", f.display()).into());
+            pb.suspend(|| eprintln!("{}", String::from_utf8_lossy(&output.stderr)));
+            return Err(format!("Failed to uncompress {}", f.display()));
         }
 
         if verbose {
-            println!("{}", String::from_utf8_lossy(&output.stdout));
+            pb.suspend(|| println!("{}", String::from_utf8_lossy(&output.stdout)));
         }
 
         pb.inc(1);
-    }
+        Ok(())
+    })?;
 
     pb.finish();
 
@@ -202,6 +290,7 @@ fn _png_conv(
     source_dir: &Path,
     output_dir: &Path,
     verbose: bool,
+    target_format: TargetFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let entries = WalkDir::new(source_dir)
         .into_iter()
@@ -213,7 +302,7 @@ fn _png_conv(
             e.path()
                 .extension()
                 .and_then(|s| s.to_str())
-                .map_or(true, |ext| !matches!(ext, "jpg" | "png" | "jpeg"))
+                .map_or(true, |ext| !target_format.is_converted(ext))
         });
 
 
@@ -228,29 +317,31 @@ fn _png_conv(
         .unwrap()
         .progress_chars("#>-"));
 
-    for raw_pic in raw_pictures {
+    raw_pictures.into_par_iter().try_for_each(|raw_pic| {
         let result = Command::new(config.img_cmd_full())
-            .args(["-t", "PNG", raw_pic.path().to_str().unwrap()])
+            .args(["-t", target_format.garbro_name(), raw_pic.path().to_str().unwrap()])
             .current_dir(output_dir)
-            .output()?;
+            .output()
+            .map_err(|e| format!("Failed to convert {}: {}", raw_pic.path().display(), e))?;
 
         if !result.status.success() {
-            eprintln!("{}", String::from_utf8_lossy(&result.stderr));
-            return Err(format!("Failed to convert {}", raw_pic.path().display()).into());
+            pb.suspend(|| eprintln!("{}", String::from_utf8_lossy(&result.stderr)));
+            return Err(format!("Failed to convert {}", raw_pic.path().display()));
         }
 
         if verbose {
-            println!("{}", String::from_utf8_lossy(&result.stdout));
+            pb.suspend(|| println!("{}", String::from_utf8_lossy(&result.stdout)));
         }
 
         pb.inc(1);
-    }
+        Ok(())
+    })?;
 
     pb.finish();
 
     for pic in not_convert {
         if pic.path().parent() != Some(output_dir) {
-            copy(&pic.path(), output_dir)?;
+            copy(pic.path(), output_dir.join(pic.file_name()))?;
         }
     }
 
@@ -262,9 +353,10 @@ fn to_png(
     root_paths: &[PathBuf],
     output_dir: &Path,
     verbose: bool,
+    target_format: TargetFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
     for d_path in root_paths {
-        _png_conv(config, d_path, output_dir, verbose)?;
+        _png_conv(config, d_path, output_dir, verbose, target_format)?;
     }
 
     Ok(())
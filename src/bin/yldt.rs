@@ -1,8 +1,60 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
 use rayon::prelude::*;
+use serde::Serialize;
 use std::fs;
+use std::io;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How files are placed into the output dataset
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TransferMode {
+    /// Copy files, leaving the sources untouched
+    Copy,
+    /// Move files (falls back to copy + delete across devices)
+    Move,
+    /// Create symlinks pointing at the source files
+    Symlink,
+}
+
+impl TransferMode {
+    fn verb(self) -> &'static str {
+        match self {
+            TransferMode::Copy => "copy",
+            TransferMode::Move => "move",
+            TransferMode::Symlink => "symlink",
+        }
+    }
+
+    fn transfer(self, src: &Path, dst: &Path) -> io::Result<()> {
+        match self {
+            TransferMode::Copy => fs::copy(src, dst).map(|_| ()),
+            TransferMode::Move => {
+                if fs::rename(src, dst).is_err() {
+                    fs::copy(src, dst)?;
+                    fs::remove_file(src)?;
+                }
+                Ok(())
+            }
+            TransferMode::Symlink => {
+                // Links are resolved relative to their own directory, so point at absolute paths.
+                let target = fs::canonicalize(src)?;
+                #[cfg(unix)]
+                {
+                    std::os::unix::fs::symlink(target, dst)
+                }
+                #[cfg(windows)]
+                {
+                    std::os::windows::fs::symlink_file(target, dst)
+                }
+            }
+        }
+    }
+}
 
 #[derive(Parser)]
 #[command(name = "yldt")]
@@ -39,6 +91,19 @@ struct Args {
     /// Image file extension
     #[arg(long, default_value = "jpg")]
     image_ext: String,
+
+    /// How to place files into the output dataset
+    #[arg(long, value_enum, default_value = "copy")]
+    mode: TransferMode,
+
+    /// Seed for shuffling before the split (random if not given)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Abort on malformed label files instead of skipping them, and skip label files
+    /// referencing a class id missing from the classes file
+    #[arg(long)]
+    strict: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -70,17 +135,20 @@ fn split_dataset(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
     let valid_files = collect_valid_file_pairs(args)?;
 
     // Split dataset
-    let (train_files, val_files) = split_files(&valid_files, args.train_ratio, args.no_validation);
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("🎲 Shuffle seed: {}", seed);
+    let (train_files, val_files) =
+        split_files(&valid_files, args.train_ratio, args.no_validation, seed);
 
     // Create output directory structure
     if !args.dry_run {
         create_output_dirs(&args.output_dir, args.no_validation)?;
     }
 
-    // Copy files
-    copy_files(&train_files, args, "train")?;
+    // Copy, move or link files
+    transfer_files(&train_files, args, "train")?;
     if !args.no_validation && !val_files.is_empty() {
-        copy_files(&val_files, args, "val")?;
+        transfer_files(&val_files, args, "val")?;
     }
 
     Ok(())
@@ -142,7 +210,55 @@ fn collect_image_files(
     Ok(image_files)
 }
 
-fn find_valid_pairs(image_files: Vec<String>, label_dir: &Path) -> Vec<String> {
+/// Checks that every line is a YOLO bbox (`class cx cy w h`) or polygon
+/// (`class x1 y1 x2 y2 ...`) with coordinates normalized to 0..1.
+///
+/// Returns the highest class id referenced, or a description of the first malformed line.
+fn validate_label_file(label_path: &Path) -> Result<Option<usize>, String> {
+    let content = fs::read_to_string(label_path).map_err(|e| format!("unreadable: {}", e))?;
+
+    let mut max_class = None;
+    for (line_no, line) in content.lines().enumerate() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let malformed = |reason: &str| format!("line {}: {} ({:?})", line_no + 1, reason, line);
+
+        let Ok(class_id) = tokens[0].parse::<usize>() else {
+            return Err(malformed("class id is not a non-negative integer"));
+        };
+        max_class = max_class.max(Some(class_id));
+
+        let coords = &tokens[1..];
+        let is_bbox = coords.len() == 4;
+        let is_polygon = coords.len() >= 6 && coords.len().is_multiple_of(2);
+        if !is_bbox && !is_polygon {
+            return Err(malformed(
+                "expected 4 bbox values or an even number (>= 6) of polygon coordinates",
+            ));
+        }
+
+        for coord in coords {
+            match coord.parse::<f32>() {
+                Ok(v) if (0.0..=1.0).contains(&v) => {}
+                Ok(_) => return Err(malformed("coordinate outside the normalized 0..1 range")),
+                Err(_) => return Err(malformed("coordinate is not a number")),
+            }
+        }
+    }
+
+    Ok(max_class)
+}
+
+/// Checks label files against `class_count` classes when given; out-of-range class ids are
+/// reported, and those files are skipped under `strict`.
+fn find_valid_pairs(
+    image_files: Vec<String>,
+    label_dir: &Path,
+    class_count: Option<usize>,
+    strict: bool,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let pb = ProgressBar::new(image_files.len() as u64);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -152,25 +268,72 @@ fn find_valid_pairs(image_files: Vec<String>, label_dir: &Path) -> Vec<String> {
     );
     pb.set_message("Finding valid file pairs");
 
+    let malformed_count = AtomicUsize::new(0);
+    let out_of_range_count = AtomicUsize::new(0);
     let valid_files: Vec<String> = image_files
         .par_iter()
         .filter_map(|image_name| {
             pb.inc(1);
             let label_path = label_dir.join(format!("{}.txt", image_name));
-            if label_path.exists() {
-                Some(image_name.clone())
-            } else {
+            if !label_path.exists() {
                 eprintln!(
                     "⚠️  Warning: Corresponding label file not found: {}.txt",
                     image_name
                 );
-                None
+                return None;
+            }
+            match (validate_label_file(&label_path), class_count) {
+                (Ok(Some(max_class)), Some(count)) if max_class >= count => {
+                    eprintln!(
+                        "⚠️  Warning: Label file {}.txt references class {}, but only {} class(es) are defined",
+                        image_name, max_class, count
+                    );
+                    out_of_range_count.fetch_add(1, Ordering::Relaxed);
+                    (!strict).then(|| image_name.clone())
+                }
+                (Ok(_), _) => Some(image_name.clone()),
+                (Err(reason), _) => {
+                    eprintln!(
+                        "⚠️  Warning: Malformed label file {}.txt, {}",
+                        image_name, reason
+                    );
+                    malformed_count.fetch_add(1, Ordering::Relaxed);
+                    None
+                }
             }
         })
         .collect();
 
     pb.finish_with_message("File pair validation completed");
-    valid_files
+
+    let malformed_count = malformed_count.into_inner();
+    if malformed_count > 0 {
+        if strict {
+            return Err(format!(
+                "{} malformed label file(s) found, aborting (--strict)",
+                malformed_count
+            )
+            .into());
+        }
+        println!("⚠️  Skipped {} malformed label file(s)", malformed_count);
+    }
+
+    let out_of_range_count = out_of_range_count.into_inner();
+    if out_of_range_count > 0 {
+        if strict {
+            println!(
+                "⚠️  Skipped {} label file(s) with out-of-range class ids",
+                out_of_range_count
+            );
+        } else {
+            println!(
+                "⚠️  {} label file(s) reference out-of-range class ids, use --strict to skip them",
+                out_of_range_count
+            );
+        }
+    }
+
+    Ok(valid_files)
 }
 
 fn collect_valid_file_pairs(args: &Args) -> Result<Vec<String>, Box<dyn std::error::Error>> {
@@ -180,8 +343,11 @@ fn collect_valid_file_pairs(args: &Args) -> Result<Vec<String>, Box<dyn std::err
     // Collect image files
     let image_files = collect_image_files(image_dir, &args.image_ext)?;
 
-    // Find valid paired files
-    let valid_files = find_valid_pairs(image_files, label_dir);
+    // Find valid paired files, checking class ids when a classes file is available
+    let class_count = read_classes(&args.classes_file)?
+        .map(|classes| classes.len())
+        .filter(|&count| count > 0);
+    let valid_files = find_valid_pairs(image_files, label_dir, class_count, args.strict)?;
 
     if valid_files.is_empty() {
         return Err("No paired image and label files found".into());
@@ -191,29 +357,21 @@ fn collect_valid_file_pairs(args: &Args) -> Result<Vec<String>, Box<dyn std::err
     Ok(valid_files)
 }
 
-fn shuffle_files(files: Vec<String>) -> Vec<String> {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-
-    let mut files_with_hash: Vec<(String, u64)> = files
-        .into_iter()
-        .map(|f| {
-            let mut hasher = DefaultHasher::new();
-            f.hash(&mut hasher);
-            (f, hasher.finish())
-        })
-        .collect();
-
-    files_with_hash.sort_by_key(|(_, hash)| *hash);
-    files_with_hash.into_iter().map(|(f, _)| f).collect()
+/// Shuffles files reproducibly: the same seed and file set always give the same order.
+fn shuffle_files(mut files: Vec<String>, seed: u64) -> Vec<String> {
+    // Directory listing order is platform dependent, so start from a sorted list.
+    files.sort();
+    files.shuffle(&mut StdRng::seed_from_u64(seed));
+    files
 }
 
 fn split_files(
     valid_files: &[String],
     train_ratio: f32,
     no_validation: bool,
+    seed: u64,
 ) -> (Vec<String>, Vec<String>) {
-    let shuffled_files = shuffle_files(valid_files.to_vec());
+    let shuffled_files = shuffle_files(valid_files.to_vec(), seed);
 
     let split_idx = (train_ratio * shuffled_files.len() as f32) as usize;
     let train_files = shuffled_files[..split_idx].to_vec();
@@ -251,7 +409,7 @@ fn create_output_dirs(
     Ok(())
 }
 
-fn copy_files(
+fn transfer_files(
     files: &[String],
     args: &Args,
     split_name: &str,
@@ -263,7 +421,8 @@ fn copy_files(
             .unwrap()
             .progress_chars("#>-"),
     );
-    pb.set_message(format!("Copying {} files", split_name));
+    let verb = args.mode.verb();
+    pb.set_message(format!("Running {} for {} files", verb, split_name));
 
     if args.dry_run {
         for file_name in files {
@@ -280,8 +439,13 @@ fn copy_files(
                 .join("labels")
                 .join(format!("{}.txt", file_name));
 
-            println!("  {} -> {}", src_img.display(), dst_img.display());
-            println!("  {} -> {}", src_label.display(), dst_label.display());
+            println!("  {} {} -> {}", verb, src_img.display(), dst_img.display());
+            println!(
+                "  {} {} -> {}",
+                verb,
+                src_label.display(),
+                dst_label.display()
+            );
             pb.inc(1);
         }
     } else {
@@ -302,9 +466,11 @@ fn copy_files(
                         .join("labels")
                         .join(format!("{}.txt", file_name));
 
-                    fs::copy(&src_img, &dst_img)
+                    args.mode
+                        .transfer(&src_img, &dst_img)
                         .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
-                    fs::copy(&src_label, &dst_label)
+                    args.mode
+                        .transfer(&src_label, &dst_label)
                         .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
                     pb.inc(1);
                     Ok(())
@@ -314,60 +480,62 @@ fn copy_files(
 
         match results {
             Ok(_) => {}
-            Err(e) => return Err(format!("File copy error: {}", e).into()),
+            Err(e) => return Err(format!("File {} error: {}", verb, e).into()),
         }
     }
 
-    pb.finish_with_message(format!("{} set file copying completed", split_name));
+    pb.finish_with_message(format!("{} set file {} completed", split_name, verb));
     Ok(())
 }
 
-fn generate_yaml(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
-    // Read classes file
-    let classes = if Path::new(&args.classes_file).exists() {
-        let content = fs::read_to_string(&args.classes_file)?;
+/// Contents of the Ultralytics `data.yaml`.
+#[derive(Serialize)]
+struct DataYaml {
+    train: String,
+    /// Serialized as `null` when no validation set is created.
+    val: Option<String>,
+    nc: usize,
+    names: Vec<String>,
+}
+
+/// Reads the non-empty lines of the classes file, or `None` if it does not exist.
+fn read_classes(classes_file: &str) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+    if !Path::new(classes_file).exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(classes_file)?;
+    Ok(Some(
         content
             .lines()
             .map(|line| line.trim().to_string())
             .filter(|line| !line.is_empty())
-            .collect::<Vec<String>>()
-    } else {
+            .collect(),
+    ))
+}
+
+fn generate_yaml(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    // Read classes file
+    let classes = read_classes(&args.classes_file)?.unwrap_or_else(|| {
         println!(
             "⚠️  Warning: Classes file {} does not exist, will generate empty class list",
             args.classes_file
         );
         Vec::new()
-    };
+    });
 
-    let train_path = format!("{}/train/images", args.output_dir);
-    let val_path = if args.no_validation {
-        "".to_string()
-    } else {
-        format!("{}/val/images", args.output_dir)
-    };
-
-    let yaml_content = if args.no_validation {
-        format!(
-            "train: {}\nval: \nnc: {}\nnames: {:?}",
-            train_path,
-            classes.len(),
-            classes
-        )
-    } else {
-        format!(
-            "train: {}\nval: {}\nnc: {}\nnames: {:?}",
-            train_path,
-            val_path,
-            classes.len(),
-            classes
-        )
+    let data_yaml = DataYaml {
+        train: format!("{}/train/images", args.output_dir),
+        val: (!args.no_validation).then(|| format!("{}/val/images", args.output_dir)),
+        nc: classes.len(),
+        names: classes.clone(),
     };
+    let yaml_content = serde_yaml::to_string(&data_yaml)?;
 
     let yaml_path = Path::new(&args.output_dir).join("data.yaml");
 
     if args.dry_run {
         println!("📄 Will generate data.yaml:");
-        println!("{}", yaml_content);
+        print!("{}", yaml_content);
         println!("📍 Path: {}", yaml_path.display());
     } else {
         fs::write(&yaml_path, yaml_content)?;